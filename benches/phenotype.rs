@@ -0,0 +1,186 @@
+//! Compares the evaluation of a genome by walking it's network with the compiled phenotypes.
+//!
+//! Uses the unstable benchmark harness. Run with `cargo bench --features nightly`.
+
+#![cfg_attr(feature = "nightly", feature(test))]
+
+#[cfg(feature = "nightly")]
+extern crate test;
+extern crate neat;
+
+#[cfg(feature = "nightly")]
+mod benches {
+    use test::Bencher;
+    use neat::genomes::{ActivationNodeType, NodeRole};
+    use neat::genomes::acyclic_network::{Genome, NodeType, NodeInnovation, GlobalCache,
+                                         GlobalInnovationCache, FeedForwardNetwork,
+                                         CompiledNetwork};
+    use neat::weight::Weight;
+    use std::collections::BTreeMap;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Neuron {
+        Input,
+        Hidden,
+        Output,
+    }
+
+    impl NodeType for Neuron {
+        fn accept_incoming_links(&self) -> bool {
+            *self != Neuron::Input
+        }
+        fn accept_outgoing_links(&self) -> bool {
+            *self != Neuron::Output
+        }
+    }
+
+    impl ActivationNodeType for Neuron {
+        fn node_role(&self) -> NodeRole {
+            match *self {
+                Neuron::Input => NodeRole::Input,
+                Neuron::Hidden => NodeRole::Hidden,
+                Neuron::Output => NodeRole::Output,
+            }
+        }
+        fn activate(&self, input: f64) -> f64 {
+            1.0 / (1.0 + (-input).exp())
+        }
+    }
+
+    const INPUTS: usize = 8;
+    const HIDDEN: usize = 32;
+    const OUTPUTS: usize = 4;
+    const SAMPLES: usize = 1000;
+
+    fn add_layer(genome: &mut Genome<Neuron>,
+                 cache: &mut GlobalInnovationCache,
+                 n: usize,
+                 node_type: Neuron)
+                 -> Vec<NodeInnovation> {
+        (0..n)
+            .map(|_| {
+                let ni = cache.create_node_innovation();
+                genome.add_node(ni, node_type.clone());
+                ni
+            })
+            .collect()
+    }
+
+    /// A fully connected genome with one hidden layer.
+
+    fn layered_genome() -> Genome<Neuron> {
+        let mut cache = GlobalInnovationCache::new();
+        let mut genome = Genome::new();
+
+        let inputs = add_layer(&mut genome, &mut cache, INPUTS, Neuron::Input);
+        let hidden = add_layer(&mut genome, &mut cache, HIDDEN, Neuron::Hidden);
+        let outputs = add_layer(&mut genome, &mut cache, OUTPUTS, Neuron::Output);
+
+        for (sources, targets) in vec![(&inputs, &hidden), (&hidden, &outputs)] {
+            for (i, &source) in sources.iter().enumerate() {
+                for (j, &target) in targets.iter().enumerate() {
+                    let weight = Weight(((i * 7 + j * 3) % 11) as f64 / 11.0 - 0.5);
+                    genome.add_link(source,
+                                    target,
+                                    cache.get_or_create_link_innovation(source, target),
+                                    weight);
+                }
+            }
+        }
+
+        genome
+    }
+
+    fn samples() -> Vec<Vec<f64>> {
+        (0..SAMPLES)
+            .map(|s| (0..INPUTS).map(|i| ((s + i) % 5) as f64 / 5.0).collect())
+            .collect()
+    }
+
+    /// Evaluates `genome` for `inputs` by walking the link lists of it's network, as done before
+    /// compiling the phenotype. The activation of each node is pushed along it's outgoing links.
+    /// This requires the network to store the nodes in topological order, which holds for
+    /// `layered_genome`.
+
+    fn naive_activate(genome: &Genome<Neuron>, inputs: &[f64]) -> Vec<f64> {
+        let network = genome.network();
+        let mut sums: BTreeMap<NodeInnovation, f64> = BTreeMap::new();
+        let mut next_input = inputs.iter();
+        let mut outputs = Vec::new();
+
+        network.each_node_with_index(|node, node_index| {
+            let node_innovation: NodeInnovation = node.external_node_id().into();
+            let role = node.node_type().node_role();
+            let value = match role {
+                NodeRole::Input => *next_input.next().unwrap(),
+                NodeRole::Bias => 1.0,
+                NodeRole::Hidden | NodeRole::Output => {
+                    let sum = sums.get(&node_innovation).cloned().unwrap_or(0.0);
+                    let params = genome.node_params(node_innovation).unwrap();
+                    node.node_type().activate(params.gain.0 * sum + params.bias.0)
+                }
+            };
+            if role == NodeRole::Output {
+                outputs.push(value);
+            }
+
+            for (_, link) in network.link_iter_for_node(node_index) {
+                if link.is_active() {
+                    let target: NodeInnovation = network.node(link.target_node_index())
+                                                        .external_node_id()
+                                                        .into();
+                    *sums.entry(target).or_insert(0.0) += link.weight().0 * value;
+                }
+            }
+        });
+
+        outputs
+    }
+
+    #[bench]
+    fn bench_naive_activate(b: &mut Bencher) {
+        let genome = layered_genome();
+        let inputs = samples();
+        b.iter(|| {
+            let mut sum = 0.0;
+            for input in inputs.iter() {
+                sum += naive_activate(&genome, input)[0];
+            }
+            sum
+        });
+    }
+
+    #[bench]
+    fn bench_feed_forward_activate(b: &mut Bencher) {
+        let net = FeedForwardNetwork::from_genome(&layered_genome());
+        let inputs = samples();
+        b.iter(|| {
+            let mut sum = 0.0;
+            for input in inputs.iter() {
+                sum += net.activate(input)[0];
+            }
+            sum
+        });
+    }
+
+    #[bench]
+    fn bench_compiled_activate_batch(b: &mut Bencher) {
+        let net = CompiledNetwork::from_genome(&layered_genome());
+        let inputs = samples();
+        b.iter(|| net.activate_batch(&inputs));
+    }
+
+    #[test]
+    fn test_naive_activate() {
+        let genome = layered_genome();
+        let net = FeedForwardNetwork::from_genome(&genome);
+        for input in samples().iter().take(10) {
+            let expected = net.activate(input);
+            let actual = naive_activate(&genome, input);
+            assert_eq!(expected.len(), actual.len());
+            for (e, a) in expected.iter().zip(actual.iter()) {
+                assert!((e - a).abs() < 1e-9);
+            }
+        }
+    }
+}
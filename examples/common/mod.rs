@@ -5,6 +5,7 @@ use graph_neighbor_matching::{SimilarityMatrix, ScoreNorm, WeightedNodeColors, N
 use graph_neighbor_matching::graph::OwnedGraph;
 use graph_neighbor_matching::{Graph, Edges};
 use neat::genomes::acyclic_network::NodeType;
+use neat::persist::{Persist, Reader, PersistError};
 use graph_io_gml::parse_gml;
 use asexp::Sexp;
 use petgraph::Directed;
@@ -157,6 +158,28 @@ impl NodeType for Neuron {
     }
 }
 
+/// Written with the names used by `convert_neuron_from_str`.
+
+impl Persist for Neuron {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        let name = match *self {
+            Neuron::Input => "input",
+            Neuron::Output => "output",
+            Neuron::Hidden => "hidden",
+        };
+        write!(wr, "{} ", name)
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        match try!(rd.next()) {
+            "input" => Ok(Neuron::Input),
+            "output" => Ok(Neuron::Output),
+            "hidden" => Ok(Neuron::Hidden),
+            name => Err(PersistError::Invalid(format!("unknown node type {}", name))),
+        }
+    }
+}
+
 pub fn convert_neuron_from_str(s: &str) -> Neuron {
     match s {
         "input" => Neuron::Input,
@@ -1,9 +1,9 @@
 use std::env;
-use neat::crossover::ProbabilisticCrossover;
-use neat::mutate::MutateMethodWeighting;
+use neat::crossover::{ProbabilisticCrossover, BlendMethod};
+use neat::mutate::{MutateMethodWeighting, MutationSchedule, MutationCount};
 use neat::weight::{WeightRange, WeightPerturbanceMethod};
 use neat::prob::Prob;
-use neat::genomes::acyclic_network::GenomeDistance;
+use neat::genomes::acyclic_network::{GenomeDistance, PruneOptions, SelfAdaptation};
 use asexp::Sexp;
 use std::collections::BTreeMap;
 use std::fs::File;
@@ -15,8 +15,22 @@ pub struct Configuration {
     population_size: usize,
     edge_score: bool,
     target_graph_file: Option<String>,
+    ancestry_file: Option<String>,
+    best_individual_file: Option<String>,
+    checkpoint_file: Option<String>,
+    initial_individual_file: Option<String>,
+    seed: Option<u64>,
 
     mutate_method_weighting: MutateMethodWeighting,
+    mutation_schedule: MutationSchedule,
+    bridge_deleted_nodes: bool,
+    prune_dead_nodes: bool,
+    prune_disabled_links: bool,
+    max_disabled_link_age: usize,
+    validate_offspring: bool,
+    reset_split_innovations: bool,
+    weight_perturbance: WeightPerturbanceMethod,
+    self_adaptation: Option<SelfAdaptation>,
     probabilistic_crossover: ProbabilisticCrossover,
     
     p_crossover: Prob,
@@ -103,15 +117,30 @@ impl Configuration {
                 w_add_connection: 10,
                 w_enable_connection: 1,
                 w_delete_connection: 1,
+                w_delete_node: 0,
                 w_add_node: 1,
+                w_change_node_type: 0,
+                w_modify_node_params: 0,
             },
 
+            mutation_schedule: MutationSchedule::single(),
+
+            bridge_deleted_nodes: false,
+            prune_dead_nodes: false,
+            prune_disabled_links: false,
+            max_disabled_link_age: 0,
+            validate_offspring: false,
+            reset_split_innovations: false,
+            weight_perturbance: WeightPerturbanceMethod::JiggleUniform{range: WeightRange::bipolar(0.1)},
+            self_adaptation: None,
+
             probabilistic_crossover: ProbabilisticCrossover {
                 prob_match_left: Prob::new(0.5), // NEAT always selects a random parent for matching genes
                 prob_disjoint_left: Prob::new(0.9),
                 prob_excess_left: Prob::new(0.9),
                 prob_disjoint_right: Prob::new(0.15),
                 prob_excess_right: Prob::new(0.15),
+                blend: BlendMethod::Select,
             },
 
             p_crossover: Prob::new(0.5),
@@ -125,6 +154,10 @@ impl Configuration {
                 excess: 1.0,
                 disjoint: 1.0,
                 weight: 0.0,
+                node_params: 0.0,
+                node_excess: 0.0,
+                node_disjoint: 0.0,
+                node_type: 0.0,
             },
 
             stop_after_iterations: 100,
@@ -132,6 +165,11 @@ impl Configuration {
             num_niches: 5,
 
             target_graph_file: None,
+            ancestry_file: None,
+            best_individual_file: None,
+            checkpoint_file: None,
+            initial_individual_file: None,
+            seed: None,
         }
     }
 
@@ -151,9 +189,55 @@ impl Configuration {
 
         if let Some(val) = parse_uint(&map, "w_modify_weight") { cfg.mutate_method_weighting.w_modify_weight = val as u32; }
         if let Some(val) = parse_uint(&map, "w_add_node") { cfg.mutate_method_weighting.w_add_node = val as u32; }
+        if let Some(val) = parse_uint(&map, "w_change_node_type") { cfg.mutate_method_weighting.w_change_node_type = val as u32; }
         if let Some(val) = parse_uint(&map, "w_add_connection") { cfg.mutate_method_weighting.w_add_connection = val as u32; }
         if let Some(val) = parse_uint(&map, "w_enable_connection") { cfg.mutate_method_weighting.w_enable_connection = val as u32; }
         if let Some(val) = parse_uint(&map, "w_delete_connection") { cfg.mutate_method_weighting.w_delete_connection = val as u32; }
+        if let Some(val) = parse_uint(&map, "w_delete_node") { cfg.mutate_method_weighting.w_delete_node = val as u32; }
+        if let Some(val) = parse_float(&map, "mutation_count_lambda") {
+            assert!(val >= 0.0);
+            cfg.mutation_schedule.count = MutationCount::Poisson { lambda: val };
+        }
+        if let Some(val) = parse_bool(&map, "mutate_after_crossover") { cfg.mutation_schedule.mutate_after_crossover = val; }
+        if let Some(val) = parse_bool(&map, "bridge_deleted_nodes") { cfg.bridge_deleted_nodes = val; }
+        if let Some(val) = parse_bool(&map, "prune_dead_nodes") { cfg.prune_dead_nodes = val; }
+        if let Some(val) = parse_bool(&map, "prune_disabled_links") { cfg.prune_disabled_links = val; }
+        if let Some(val) = parse_uint(&map, "max_disabled_link_age") { cfg.max_disabled_link_age = val as usize; }
+        if let Some(val) = parse_bool(&map, "validate_offspring") { cfg.validate_offspring = val; }
+        if let Some(val) = parse_bool(&map, "reset_split_innovations") { cfg.reset_split_innovations = val; }
+        if let Some(val) = parse_uint(&map, "w_modify_node_params") { cfg.mutate_method_weighting.w_modify_node_params = val as u32; }
+
+        if let Some(method) = parse_string(&map, "weight_perturbance") {
+            let param = parse_float(&map, "weight_perturbance_param").unwrap_or(0.1);
+            match &method[..] {
+                // a zero range, sigma or scale would never change a weight
+                "uniform" | "gaussian" | "cauchy" => {
+                    assert!(param > 0.0, "weight_perturbance_param of {} must be > 0", method)
+                }
+                "polynomial" => {
+                    assert!(param >= 0.0, "weight_perturbance_param of {} must be >= 0", method)
+                }
+                _ => {}
+            }
+            cfg.weight_perturbance = match &method[..] {
+                "uniform" => WeightPerturbanceMethod::JiggleUniform{range: WeightRange::bipolar(param)},
+                "gaussian" => WeightPerturbanceMethod::JiggleGaussian{sigma: param},
+                "cauchy" => WeightPerturbanceMethod::JiggleCauchy{scale: param},
+                "polynomial" => WeightPerturbanceMethod::Polynomial{eta: param},
+                "random" => WeightPerturbanceMethod::Random,
+                _ => panic!("Invalid weight_perturbance: {}", method),
+            };
+        }
+
+        if parse_bool(&map, "self_adaptation") == Some(true) {
+            cfg.self_adaptation = Some(SelfAdaptation {
+                initial_sigma: parse_float(&map, "self_adaptation_sigma").unwrap_or(0.1),
+                tau_global: parse_float(&map, "self_adaptation_tau_global").unwrap_or(0.2),
+                tau_local: parse_float(&map, "self_adaptation_tau_local").unwrap_or(0.1),
+                min_sigma: parse_float(&map, "self_adaptation_min_sigma").unwrap_or(0.001),
+                per_link: parse_bool(&map, "self_adaptation_per_link").unwrap_or(false),
+            });
+        }
 
         if let Some(val) = parse_float(&map, "elite_percentage") {
             assert!(val >= 0.0 && val <= 100.0);
@@ -181,6 +265,22 @@ impl Configuration {
             assert!(val >= 0.0);
             cfg.genome_compatibility.weight= val;
         }
+        if let Some(val) = parse_float(&map, "compatibility_node_params") {
+            assert!(val >= 0.0);
+            cfg.genome_compatibility.node_params = val;
+        }
+        if let Some(val) = parse_float(&map, "compatibility_node_excess") {
+            assert!(val >= 0.0);
+            cfg.genome_compatibility.node_excess = val;
+        }
+        if let Some(val) = parse_float(&map, "compatibility_node_disjoint") {
+            assert!(val >= 0.0);
+            cfg.genome_compatibility.node_disjoint = val;
+        }
+        if let Some(val) = parse_float(&map, "compatibility_node_type") {
+            assert!(val >= 0.0);
+            cfg.genome_compatibility.node_type = val;
+        }
 
         if let Some(val) = parse_prob(&map, "px_match_left") {
             cfg.probabilistic_crossover.prob_match_left = val;
@@ -197,6 +297,14 @@ impl Configuration {
         if let Some(val) = parse_prob(&map, "px_excess_right") {
             cfg.probabilistic_crossover.prob_excess_right = val;
         }
+        if let Some(method) = parse_string(&map, "px_blend") {
+            cfg.probabilistic_crossover.blend = match &method[..] {
+                "select" => BlendMethod::Select,
+                "average" => BlendMethod::Average,
+                "blx" => BlendMethod::BlxAlpha{alpha: parse_float(&map, "px_blend_alpha").unwrap_or(0.5)},
+                _ => panic!("Invalid px_blend: {}", method),
+            };
+        }
 
         if let Some(val) = parse_prob(&map, "p_crossover") {
             cfg.p_crossover = val;
@@ -212,6 +320,11 @@ impl Configuration {
         if let Some(val) = parse_uint(&map, "num_niches") { cfg.num_niches = val as usize; }
 
         if let Some(val) = parse_string(&map, "target_graph_file") { cfg.target_graph_file = Some(val); }
+        if let Some(val) = parse_string(&map, "ancestry_file") { cfg.ancestry_file = Some(val); }
+        if let Some(val) = parse_string(&map, "best_individual_file") { cfg.best_individual_file = Some(val); }
+        if let Some(val) = parse_string(&map, "checkpoint_file") { cfg.checkpoint_file = Some(val); }
+        if let Some(val) = parse_string(&map, "initial_individual_file") { cfg.initial_individual_file = Some(val); }
+        if let Some(val) = parse_uint(&map, "seed") { cfg.seed = Some(val); }
 
         cfg
     }
@@ -225,7 +338,11 @@ impl Configuration {
     }
 
     pub fn weight_perturbance(&self) -> WeightPerturbanceMethod {
-        WeightPerturbanceMethod::JiggleUniform{range: WeightRange::bipolar(0.1)}
+        self.weight_perturbance
+    }
+
+    pub fn self_adaptation(&self) -> Option<SelfAdaptation> {
+        self.self_adaptation
     }
 
     pub fn elite_percentage(&self) -> Closed01<f64> {
@@ -276,6 +393,32 @@ impl Configuration {
         }
     }
 
+    /// If set, the ancestry of the best individual is written to this file in DOT format.
+    pub fn ancestry_file(&self) -> Option<&str> {
+        self.ancestry_file.as_ref().map(|s| &s[..])
+    }
+
+    /// If set, the best individual is archived to this file.
+    pub fn best_individual_file(&self) -> Option<&str> {
+        self.best_individual_file.as_ref().map(|s| &s[..])
+    }
+
+    /// If set, a checkpoint is written to this file after each iteration. If the file
+    /// exists at start, the run is resumed from it.
+    pub fn checkpoint_file(&self) -> Option<&str> {
+        self.checkpoint_file.as_ref().map(|s| &s[..])
+    }
+
+    /// If set, the individual archived in this file is added to the initial population.
+    pub fn initial_individual_file(&self) -> Option<&str> {
+        self.initial_individual_file.as_ref().map(|s| &s[..])
+    }
+
+    /// The master seed of the run. If not set, a random seed is used.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
     pub fn genome_compatibility(&self) -> &GenomeDistance {
         &self.genome_compatibility
     }
@@ -287,4 +430,32 @@ impl Configuration {
     pub fn mutate_method_weighting(&self) -> MutateMethodWeighting {
         self.mutate_method_weighting
     }
+
+    pub fn mutation_schedule(&self) -> MutationSchedule {
+        self.mutation_schedule
+    }
+
+    pub fn bridge_deleted_nodes(&self) -> bool {
+        self.bridge_deleted_nodes
+    }
+
+    pub fn prune_offspring(&self) -> Option<PruneOptions> {
+        if self.prune_dead_nodes || self.prune_disabled_links {
+            Some(PruneOptions {
+                dead_nodes: self.prune_dead_nodes,
+                disabled_links: self.prune_disabled_links,
+                max_disabled_age: self.max_disabled_link_age,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn validate_offspring(&self) -> bool {
+        self.validate_offspring
+    }
+
+    pub fn reset_split_innovations(&self) -> bool {
+        self.reset_split_innovations
+    }
 }
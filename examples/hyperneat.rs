@@ -15,8 +15,8 @@ mod config;
 
 use neat::population::{Population, Unrated, NicheRunner};
 use neat::traits::FitnessEval;
-use neat::genomes::acyclic_network::{Genome, GlobalCache, GlobalInnovationCache, Mater,
-                                     ElementStrategy};
+use neat::genomes::acyclic_network::{Genome, GlobalInnovationCache, Mater, ElementStrategy,
+                                     GenomeBuilder, InitialConnectivity};
 use neat::fitness::Fitness;
 use graph_neighbor_matching::graph::{GraphBuilder, OwnedGraph};
 use rand::Rng;
@@ -148,27 +148,19 @@ fn main() {
 
     // start with minimal random topology.
 
-    let template_genome = {
-        let mut genome = Genome::new();
-
-        // 4 inputs (x1,y1,x2,y2)
-        for _ in 0..4 {
-            genome.add_node(cache.create_node_innovation(), CppnNode::Input);
-        }
-
-        // 1 output (y)
-        genome.add_node(cache.create_node_innovation(), CppnNode::Output);
-
-        // 1 bias node
-        genome.add_node(cache.create_node_innovation(), CppnNode::Bias);
-
-        genome
-    };
+    // 4 inputs (x1,y1,x2,y2), 1 output (y) and 1 bias node.
+    let genome_builder = GenomeBuilder::new(vec![CppnNode::Input; 4],
+                                            vec![CppnNode::Output],
+                                            Some(CppnNode::Bias),
+                                            &mut cache);
 
     let mut initial_pop = Population::<_, Unrated>::new();
 
     for _ in 0..cfg.population_size() {
-        initial_pop.add_genome(Box::new(template_genome.clone()));
+        initial_pop.add_genome(Box::new(genome_builder.build(InitialConnectivity::Unconnected,
+                                                             &ES.link_weight_range(),
+                                                             &mut cache,
+                                                             &mut rng)));
     }
     assert!(initial_pop.len() == cfg.population_size());
 
@@ -177,7 +169,12 @@ fn main() {
         p_crossover_detail: cfg.probabilistic_crossover(),
         p_mutate_element: cfg.p_mutate_element(),
         weight_perturbance: cfg.weight_perturbance(),
+        self_adaptation: cfg.self_adaptation(),
         mutate_weights: cfg.mutate_method_weighting(),
+        mutation_schedule: cfg.mutation_schedule(),
+        bridge_deleted_nodes: cfg.bridge_deleted_nodes(),
+        prune_offspring: cfg.prune_offspring(),
+        validate_offspring: cfg.validate_offspring(),
         global_cache: &mut cache,
         element_strategy: &ES,
         _n: PhantomData,
@@ -190,6 +187,10 @@ fn main() {
     while niche_runner.has_next_iteration(cfg.stop_after_iters()) {
         println!("iteration: {}", niche_runner.current_iteration());
 
+        if cfg.reset_split_innovations() {
+            mater.global_cache.reset_split_node_innovations();
+        }
+
         let best_fitness = niche_runner.best_individual().fitness().get();;
         println!("best fitness: {:2}", best_fitness); 
         println!("num individuals: {}", niche_runner.num_individuals());
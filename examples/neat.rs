@@ -14,13 +14,18 @@ mod common;
 mod config;
 
 //use criterion_stats::univariate::Sample;
-use neat::population::{Population, Unrated, NicheRunner};
+use neat::population::{Population, Individual, Unrated, NicheRunner};
 use neat::traits::{FitnessEval};
-use neat::genomes::acyclic_network::{Genome, GlobalCache, GlobalInnovationCache, Mater, ElementStrategy};
+use neat::genomes::acyclic_network::{Genome, GlobalInnovationCache, Mater, ElementStrategy,
+                                     GenomeBuilder, InitialConnectivity};
 use neat::fitness::Fitness;
+use neat::persist;
+use neat::seed::SeedStream;
 use graph_neighbor_matching::graph::{OwnedGraph, GraphBuilder};
 use rand::Rng;
 use std::marker::PhantomData;
+use std::fs::File;
+use std::path::Path;
 use common::{load_graph, Neuron, convert_neuron_from_str, GraphSimilarity, NodeCount, write_gml};
 use neat::weight::{Weight, WeightRange};
 use closed01::Closed01;
@@ -71,8 +76,6 @@ impl ElementStrategy<Neuron> for ES {
 fn main() {
     env_logger::init().unwrap();
 
-    let mut rng = rand::thread_rng();
-    
     let cfg = config::Configuration::from_file();
 
     println!("{:?}", cfg);
@@ -89,54 +92,103 @@ fn main() {
         }
     };
 
-    let mut cache = GlobalInnovationCache::new();
-
-    // start with minimal random topology.
-    //
-    // Generates a template Genome with `n_inputs` input nodes and `n_outputs` output nodes.
-    // The genome will not have any link nodes.
-
-    let template_genome = {
-        let mut genome = Genome::new();
-        assert!(node_count.inputs > 0 && node_count.outputs > 0);
-
-        for _ in 0..node_count.inputs {
-            genome.add_node(cache.create_node_innovation(), Neuron::Input);
+    let resumed = match cfg.checkpoint_file() {
+        Some(checkpoint_file) if Path::new(checkpoint_file).exists() => {
+            let checkpoint = NicheRunner::load_checkpoint::<_, (GlobalInnovationCache, SeedStream)>(
+                &fitness_evaluator, checkpoint_file);
+            match checkpoint {
+                Ok((niche_runner, (cache, master))) => {
+                    println!("resuming from {} after iteration {}",
+                             checkpoint_file, niche_runner.current_iteration());
+                    Some((niche_runner, cache, master))
+                }
+                Err(err) => panic!("Failed to load {}: {:?}", checkpoint_file, err),
+            }
         }
-        for _ in 0..node_count.outputs {
-            genome.add_node(cache.create_node_innovation(), Neuron::Output);
+        _ => None,
+    };
+
+    let (mut niche_runner, mut cache, master) = match resumed {
+        Some(resumed) => resumed,
+        None => {
+            // the whole run only depends on this seed.
+            let master = SeedStream::new(cfg.seed().unwrap_or_else(|| rand::thread_rng().gen()));
+            let mut rng = master.split(0).rng();
+
+            // the niches are reproduced in parallel, each with it's own stage of the cache.
+            let mut cache = GlobalInnovationCache::new();
+
+            // start with minimal random topology.
+            //
+            // Generates Genomes with `n_inputs` input nodes and `n_outputs` output nodes.
+            // The genomes will not have any link nodes.
+
+            assert!(node_count.inputs > 0 && node_count.outputs > 0);
+            let genome_builder = GenomeBuilder::new(vec![Neuron::Input; node_count.inputs],
+                                                    vec![Neuron::Output; node_count.outputs],
+                                                    None,
+                                                    &mut cache);
+
+            // an individual archived by an earlier run on the same target graph. The input and
+            // output nodes above were created in the same order as in that run, so they match
+            // those of the archived genome. Its remaining innovations are registered before
+            // any link is created.
+            let mut initial_individual = cfg.initial_individual_file().map(|file| {
+                let individual: Individual<Genome<Neuron>> = persist::load_file(file)
+                    .unwrap_or_else(|err| panic!("Failed to load {}: {:?}", file, err));
+                cache.register_genome(individual.genome());
+                individual.into_genome()
+            });
+
+            let mut niche_runner = NicheRunner::new(&fitness_evaluator);
+
+            let niche_size = cfg.population_size() / cfg.num_niches(); 
+
+            for _ in 0..cfg.num_niches() {
+                let mut initial_pop = Population::<_, Unrated>::new();
+
+                if let Some(genome) = initial_individual.take() {
+                    initial_pop.add_genome(genome);
+                }
+
+                for _ in 0..niche_size {
+                    initial_pop.add_genome(Box::new(genome_builder.build(InitialConnectivity::Unconnected,
+                                                                         &ES.link_weight_range(),
+                                                                         &mut cache,
+                                                                         &mut rng)));
+                }
+
+                niche_runner.add_unrated_population_as_niche(initial_pop);
+            }
+
+            (niche_runner, cache, master)
         }
-        genome
     };
+    println!("seed: {}", master.seed());
 
     let mut mater = Mater {
         p_crossover: cfg.p_crossover(),
         p_crossover_detail: cfg.probabilistic_crossover(),
         p_mutate_element: cfg.p_mutate_element(),
         weight_perturbance: cfg.weight_perturbance(),
+        self_adaptation: cfg.self_adaptation(),
         mutate_weights: cfg.mutate_method_weighting(),
+        mutation_schedule: cfg.mutation_schedule(),
+        bridge_deleted_nodes: cfg.bridge_deleted_nodes(),
+        prune_offspring: cfg.prune_offspring(),
+        validate_offspring: cfg.validate_offspring(),
         global_cache: &mut cache,
         element_strategy: &ES,
         _n: PhantomData,
     };
 
-    let mut niche_runner = NicheRunner::new(&fitness_evaluator);
-
-    let niche_size = cfg.population_size() / cfg.num_niches(); 
-
-    for _ in 0..cfg.num_niches() {
-        let mut initial_pop = Population::<_, Unrated>::new();
-
-        for _ in 0..niche_size {
-            initial_pop.add_genome(Box::new(template_genome.clone()));
-        }
-
-        niche_runner.add_unrated_population_as_niche(initial_pop);
-    }
-
     while niche_runner.has_next_iteration(cfg.stop_after_iters()) {
         println!("iteration: {}", niche_runner.current_iteration());
 
+        if cfg.reset_split_innovations() {
+            mater.global_cache.reset_split_node_innovations();
+        }
+
         let best_fitness = niche_runner.best_individual().fitness().get();;
         println!("best fitness: {:2}", best_fitness); 
         println!("num individuals: {}", niche_runner.num_individuals());
@@ -146,6 +198,11 @@ fn main() {
             break;
         }
 
+        // everything random within this iteration is derived from this seed, so that a run
+        // resumed from a checkpoint continues with the same random numbers.
+        let generation_seed = master.split(niche_runner.current_iteration() as u64);
+        let mut rng = generation_seed.split(1).rng();
+
         //let samples = niche_runner.inter_niche_compatibility_distance(100, cfg.genome_compatibility(), &mut rng);
         //println!("samples: {:?}", samples);
 
@@ -173,11 +230,11 @@ fn main() {
 
         println!("num niches: {}", niche_runner.num_niches());
 
-        niche_runner.reproduce_niche_locally(cfg.population_size(),
-                                      cfg.elite_percentage(),
-                                      cfg.selection_percentage(),
-                                      &mut mater,
-                                      &mut rng);
+        niche_runner.reproduce_niche_locally_seeded(cfg.population_size(),
+                                                    cfg.elite_percentage(),
+                                                    cfg.selection_percentage(),
+                                                    &mut mater,
+                                                    &generation_seed.split(0));
 
         // If niches do not improve t=10 timesteps, redistribute them to
         // other niches.
@@ -189,6 +246,26 @@ fn main() {
         if redistributes > 0 {
             println!("{} niches redistributed", redistributes);
         }
+
+        // only the ancestry of the current population is of interest.
+        niche_runner.prune_genealogy();
+
+        if let Some(checkpoint_file) = cfg.checkpoint_file() {
+            let state = (mater.global_cache.clone(), master);
+            if let Err(err) = niche_runner.save_checkpoint(&state, checkpoint_file) {
+                println!("Failed to write {}: {}", checkpoint_file, err);
+            }
+        }
+    }
+
+    if let Some(ancestry_file) = cfg.ancestry_file() {
+        let best_id = niche_runner.best_individual().lineage().id.unwrap();
+        let result = File::create(ancestry_file).and_then(|mut file| {
+            niche_runner.genealogy().ancestry_to_dot(best_id, &mut file)
+        });
+        if let Err(err) = result {
+            println!("Failed to write {}: {}", ancestry_file, err);
+        }
     }
 
     let final_pop = niche_runner.into_population().sort();
@@ -197,6 +274,12 @@ fn main() {
         let best = final_pop.best_individual().unwrap();
         println!("best fitness: {:.3}", best.fitness().get());
         write_gml("best.gml", &genome_to_graph(best.genome()));
+        if let Some(best_individual_file) = cfg.best_individual_file() {
+            // can be reloaded with `persist::load_file` to seed a later run
+            if let Err(err) = persist::save_file(best, best_individual_file) {
+                println!("Failed to write {}: {}", best_individual_file, err);
+            }
+        }
     }
 
     for (i, ind) in final_pop.into_iter().enumerate() {
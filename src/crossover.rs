@@ -1,4 +1,6 @@
 use prob::Prob;
+use rand::Rng;
+use weight::{Weight, WeightRange};
 
 /// A specific form of crossover where the probabilities below determine from
 /// which parent a gene is taken.
@@ -20,4 +22,116 @@ pub struct ProbabilisticCrossover {
 
     /// Probability to take an excess gene from the less fit (right) parent.
     pub prob_excess_right: Prob,
+
+    /// Determines how the weights (and node parameters) of matching genes are combined.
+    pub blend: BlendMethod,
+}
+
+impl ProbabilisticCrossover {
+    /// Returns the crossover used for parents of equal fitness. Matching genes are taken from
+    /// either parent with equal probability, and disjoint and excess genes of both parents are
+    /// inherited with the probabilities of the fitter (left) parent.
+
+    pub fn symmetric(&self) -> Self {
+        ProbabilisticCrossover {
+            prob_match_left: Prob::new(0.5),
+            prob_disjoint_left: self.prob_disjoint_left,
+            prob_excess_left: self.prob_excess_left,
+            prob_disjoint_right: self.prob_disjoint_left,
+            prob_excess_right: self.prob_excess_left,
+            blend: self.blend,
+        }
+    }
+}
+
+/// Determines how the numeric values (link weights, node parameters) of matching genes are
+/// combined during crossover.
+#[derive(Debug, Copy, Clone)]
+pub enum BlendMethod {
+    /// Take the value from the parent the gene was taken from (see `prob_match_left`).
+    Select,
+
+    /// Take the mean of both values.
+    Average,
+
+    /// BLX-alpha: Take a uniformly random value within the interval spanned by both values,
+    /// extended on both sides by `alpha` times it's length. As the extended interval may exceed
+    /// the weight range, the value is clipped to it.
+    BlxAlpha {
+        alpha: f64,
+    },
+}
+
+impl BlendMethod {
+    /// Combines the values `left` and `right` of a matching gene. `selected` is the value of
+    /// the parent the gene was taken from. `range` is the range of valid values.
+
+    pub fn blend<R: Rng>(&self,
+                         left: f64,
+                         right: f64,
+                         selected: f64,
+                         range: &WeightRange,
+                         rng: &mut R)
+                         -> f64 {
+        match *self {
+            BlendMethod::Select => selected,
+            BlendMethod::Average => 0.5 * (left + right),
+            BlendMethod::BlxAlpha { alpha } => {
+                assert!(alpha >= 0.0);
+                let (min, max) = if left <= right {
+                    (left, right)
+                } else {
+                    (right, left)
+                };
+                let extension = alpha * (max - min);
+                let (low, high) = (min - extension, max + extension);
+                let value = if low < high {
+                    rng.gen_range(low, high)
+                } else {
+                    low
+                };
+                range.clip_weight(Weight(value)).0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlendMethod;
+    use weight::{Weight, WeightRange};
+    use rand::{XorShiftRng, SeedableRng};
+
+    #[test]
+    fn test_blend() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let range = WeightRange::new(2.0, -2.0);
+        assert_eq!(1.0, BlendMethod::Select.blend(0.5, 1.0, 1.0, &range, &mut rng));
+        assert_eq!(0.75, BlendMethod::Average.blend(0.5, 1.0, 1.0, &range, &mut rng));
+        assert_eq!(0.5,
+                   BlendMethod::BlxAlpha { alpha: 0.5 }.blend(0.5, 0.5, 0.5, &range, &mut rng));
+
+        for _ in 0..100 {
+            let value = BlendMethod::BlxAlpha { alpha: 0.5 }
+                            .blend(1.0, 0.0, 1.0, &range, &mut rng);
+            assert!(value >= -0.5 && value <= 1.5);
+        }
+    }
+
+    #[test]
+    fn test_blend_clips_to_range() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let range = WeightRange::new(1.0, -1.0);
+        let blx = BlendMethod::BlxAlpha { alpha: 1.0 };
+        let mut clipped = 0;
+        for _ in 0..100 {
+            let value = blx.blend(1.0, -1.0, 1.0, &range, &mut rng);
+            assert!(range.in_range(Weight(value)));
+            if value == 1.0 || value == -1.0 {
+                clipped += 1;
+            }
+        }
+        // the extended interval is [-3, 3), so about two thirds of the values are clipped.
+        assert!(clipped > 30);
+    }
 }
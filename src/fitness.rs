@@ -2,6 +2,8 @@ use std::cmp::Ord;
 use std::cmp::Ordering;
 use std::ops::{Add, Div};
 use std::num::Zero;
+use std::io::{self, Write};
+use persist::{Persist, Reader, PersistError};
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Fitness(f64);
@@ -44,3 +46,18 @@ impl Ord for Fitness {
         self.0.partial_cmp(&other.0).unwrap_or(Ordering::Less)
     }
 }
+
+impl Persist for Fitness {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        self.0.persist(wr)
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        let fitness: f64 = try!(rd.atom());
+        if fitness >= 0.0 {
+            Ok(Fitness(fitness))
+        } else {
+            Err(PersistError::Invalid(format!("negative fitness {}", fitness)))
+        }
+    }
+}
@@ -4,17 +4,24 @@ pub use acyclic_network::NodeType;
 use traits::{Distance, Genotype};
 use weight::{Weight, WeightRange, WeightPerturbanceMethod};
 use alignment_metric::AlignmentMetric;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use alignment::{Alignment, align_sorted_iterators, LeftOrRight};
 use std::cmp;
 use rand::Rng;
+use rand::distributions::{Normal, IndependentSample};
 use crossover::ProbabilisticCrossover;
 use std::convert::Into;
 use std::ops::Range;
 use prob::Prob;
-use mutate::{MutateMethod, MutateMethodWeighting};
+use mutate::{MutateMethod, MutateMethodWeighting, MutationSchedule};
 use std::marker::PhantomData;
-use traits::Mate;
+use traits::{Mate, StagedMate};
+use fitness::Fitness;
+use lineage::{Operator, Contributors};
+use genomes::{ActivationNodeType, NodeRole, NodeLabel, GenomeMater};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use persist::{Persist, Reader, PersistError};
 
 #[derive(Copy, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct AnyInnovation(usize);
@@ -43,6 +50,26 @@ impl Innovation for NodeInnovation {}
 
 impl Innovation for LinkInnovation {}
 
+impl Persist for NodeInnovation {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        self.0.persist(wr)
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        rd.atom().map(NodeInnovation)
+    }
+}
+
+impl Persist for LinkInnovation {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        self.0.persist(wr)
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        rd.atom().map(LinkInnovation)
+    }
+}
+
 struct CombinedAlignmentMetric {
     node_metric: AlignmentMetric,
     link_metric: AlignmentMetric,
@@ -80,13 +107,26 @@ pub trait GlobalCache {
                                      target_node: NodeInnovation)
                                      -> LinkInnovation;
     fn create_node_innovation(&mut self) -> NodeInnovation;
+
+    /// Returns the innovation of the node which is inserted when splitting the link
+    /// `split_link`. Genomes splitting the same link should receive the same node innovation.
+    ///
+    /// The default implementation always creates a new node innovation.
+    fn get_or_create_split_node_innovation(&mut self,
+                                           _split_link: LinkInnovation)
+                                           -> NodeInnovation {
+        self.create_node_innovation()
+    }
 }
 
+#[derive(Debug, Clone)]
 pub struct GlobalInnovationCache {
     node_innovation_counter: Range<usize>,
     link_innovation_counter: Range<usize>,
     // (src_node, target_node) -> link_innovation
     link_innovation_cache: BTreeMap<(NodeInnovation, NodeInnovation), LinkInnovation>,
+    // split_link -> node_innovation
+    split_node_innovation_cache: BTreeMap<LinkInnovation, NodeInnovation>,
 }
 
 impl GlobalInnovationCache {
@@ -101,7 +141,68 @@ impl GlobalInnovationCache {
                 end: usize::max_value(),
             },
             link_innovation_cache: BTreeMap::new(),
+            split_node_innovation_cache: BTreeMap::new(),
+        }
+    }
+
+    /// Forgets all link splits seen so far. Call this at the start of each generation to only
+    /// share split node innovations between splits within the same generation, as in the
+    /// original NEAT.
+
+    pub fn reset_split_node_innovations(&mut self) {
+        self.split_node_innovation_cache.clear();
+    }
+
+    /// Makes the cache aware of the innovations used by `genome`, e.g. of a genome archived in
+    /// an earlier run. Each link of `genome` is recorded for its pair of nodes, unless the cache
+    /// already knows a link for that pair, and the counters are raised above the innovations of
+    /// `genome`, so that newly created innovations do not collide with them.
+
+    pub fn register_genome<NT: NodeType>(&mut self, genome: &Genome<NT>) {
+        if let Some(&NodeInnovation(max)) = genome.node_innovation_map.keys().max() {
+            self.node_innovation_counter.start = cmp::max(self.node_innovation_counter.start,
+                                                          max + 1);
+        }
+
+        let link_cache = &mut self.link_innovation_cache;
+        let link_counter = &mut self.link_innovation_counter;
+        genome.network.each_link_ref(|link_ref| {
+            let source_node: NodeInnovation = link_ref.external_source_node_id().into();
+            let target_node: NodeInnovation = link_ref.external_target_node_id().into();
+            let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+            link_cache.entry((source_node, target_node)).or_insert(link_innovation);
+            link_counter.start = cmp::max(link_counter.start, link_innovation.0 + 1);
+        });
+    }
+
+    /// Creates the innovations recorded by `staged` within this cache, in the order they were
+    /// recorded. Returns the mapping from the provisional innovations of `staged` to those of
+    /// this cache.
+
+    pub fn commit(&mut self, staged: StagedInnovationCache) -> InnovationMap {
+        let mut map = InnovationMap::new();
+
+        for innovation in staged.log {
+            match innovation {
+                StagedInnovation::Node(provisional, None) => {
+                    let node = self.create_node_innovation();
+                    map.nodes.insert(provisional, node);
+                }
+                StagedInnovation::Node(provisional, Some(split_link)) => {
+                    let split_link = map.link(split_link);
+                    let node = self.get_or_create_split_node_innovation(split_link);
+                    map.nodes.insert(provisional, node);
+                }
+                StagedInnovation::Link(provisional, source_node, target_node) => {
+                    let source_node = map.node(source_node);
+                    let target_node = map.node(target_node);
+                    let link = self.get_or_create_link_innovation(source_node, target_node);
+                    map.links.insert(provisional, link);
+                }
+            }
         }
+
+        map
     }
 }
 
@@ -123,16 +224,500 @@ impl GlobalCache for GlobalInnovationCache {
     fn create_node_innovation(&mut self) -> NodeInnovation {
         NodeInnovation(self.node_innovation_counter.next().unwrap())
     }
+
+    fn get_or_create_split_node_innovation(&mut self,
+                                           split_link: LinkInnovation)
+                                           -> NodeInnovation {
+        if let Some(&cached_innovation) = self.split_node_innovation_cache.get(&split_link) {
+            return cached_innovation;
+        }
+        let new_innovation = self.create_node_innovation();
+        self.split_node_innovation_cache.insert(split_link, new_innovation);
+        new_innovation
+    }
+}
+
+/// Written as `(innovation-cache next-node next-link (links (source target link)...) (splits
+/// (link node)...))`.
+
+impl Persist for GlobalInnovationCache {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr,
+                    "(innovation-cache {} {}\n  (links",
+                    self.node_innovation_counter.start,
+                    self.link_innovation_counter.start));
+        for (&(source_node, target_node), &link) in self.link_innovation_cache.iter() {
+            try!(write!(wr, " ({} {} {})", source_node.0, target_node.0, link.0));
+        }
+        try!(write!(wr, ")\n  (splits"));
+        for (&split_link, &node) in self.split_node_innovation_cache.iter() {
+            try!(write!(wr, " ({} {})", split_link.0, node.0));
+        }
+        write!(wr, "))\n")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("innovation-cache"));
+        let mut cache = GlobalInnovationCache::new();
+        cache.node_innovation_counter.start = try!(rd.atom());
+        cache.link_innovation_counter.start = try!(rd.atom());
+
+        try!(rd.open_keyword("links"));
+        while !rd.at_close() {
+            try!(rd.open());
+            let source_node = try!(NodeInnovation::restore(rd));
+            let target_node = try!(NodeInnovation::restore(rd));
+            let link = try!(LinkInnovation::restore(rd));
+            try!(rd.close());
+            cache.link_innovation_cache.insert((source_node, target_node), link);
+        }
+        try!(rd.close());
+
+        try!(rd.open_keyword("splits"));
+        while !rd.at_close() {
+            let (split_link, node) = try!(Persist::restore(rd));
+            cache.split_node_innovation_cache.insert(split_link, node);
+        }
+        try!(rd.close());
+
+        try!(rd.close());
+        Ok(cache)
+    }
+}
+
+impl<'a, C: GlobalCache + ?Sized> GlobalCache for &'a mut C {
+    fn get_or_create_link_innovation(&mut self,
+                                     source_node: NodeInnovation,
+                                     target_node: NodeInnovation)
+                                     -> LinkInnovation {
+        (**self).get_or_create_link_innovation(source_node, target_node)
+    }
+
+    fn create_node_innovation(&mut self) -> NodeInnovation {
+        (**self).create_node_innovation()
+    }
+
+    fn get_or_create_split_node_innovation(&mut self,
+                                           split_link: LinkInnovation)
+                                           -> NodeInnovation {
+        (**self).get_or_create_split_node_innovation(split_link)
+    }
+}
+
+/// A `GlobalInnovationCache` which can be shared between threads.
+///
+/// `GlobalCache` is implemented for `&ConcurrentInnovationCache`, so each thread can hold it's
+/// own `Mater` referring to the same cache. All threads receive the same link innovation for
+/// the same pair of nodes, and the same node innovation for splits of the same link. The
+/// numbers themselves depend on the order in which the threads reach the cache, see
+/// `StagedInnovationCache` for a deterministic alternative.
+
+pub struct ConcurrentInnovationCache {
+    cache: Mutex<GlobalInnovationCache>,
+}
+
+impl ConcurrentInnovationCache {
+    pub fn new() -> Self {
+        ConcurrentInnovationCache::from_cache(GlobalInnovationCache::new())
+    }
+
+    pub fn from_cache(cache: GlobalInnovationCache) -> Self {
+        ConcurrentInnovationCache { cache: Mutex::new(cache) }
+    }
+
+    pub fn into_inner(self) -> GlobalInnovationCache {
+        self.cache.into_inner().unwrap()
+    }
+
+    pub fn reset_split_node_innovations(&self) {
+        self.cache.lock().unwrap().reset_split_node_innovations();
+    }
+}
+
+impl<'a> GlobalCache for &'a ConcurrentInnovationCache {
+    fn get_or_create_link_innovation(&mut self,
+                                     source_node: NodeInnovation,
+                                     target_node: NodeInnovation)
+                                     -> LinkInnovation {
+        self.cache.lock().unwrap().get_or_create_link_innovation(source_node, target_node)
+    }
+
+    fn create_node_innovation(&mut self) -> NodeInnovation {
+        self.cache.lock().unwrap().create_node_innovation()
+    }
+
+    fn get_or_create_split_node_innovation(&mut self,
+                                           split_link: LinkInnovation)
+                                           -> NodeInnovation {
+        self.cache.lock().unwrap().get_or_create_split_node_innovation(split_link)
+    }
+}
+
+impl GlobalCache for ConcurrentInnovationCache {
+    fn get_or_create_link_innovation(&mut self,
+                                     source_node: NodeInnovation,
+                                     target_node: NodeInnovation)
+                                     -> LinkInnovation {
+        self.cache.get_mut().unwrap().get_or_create_link_innovation(source_node, target_node)
+    }
+
+    fn create_node_innovation(&mut self) -> NodeInnovation {
+        self.cache.get_mut().unwrap().create_node_innovation()
+    }
+
+    fn get_or_create_split_node_innovation(&mut self,
+                                           split_link: LinkInnovation)
+                                           -> NodeInnovation {
+        self.cache.get_mut().unwrap().get_or_create_split_node_innovation(split_link)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum StagedInnovation {
+    // provisional node innovation, split link
+    Node(NodeInnovation, Option<LinkInnovation>),
+    // provisional link innovation, source node, target node
+    Link(LinkInnovation, NodeInnovation, NodeInnovation),
+}
+
+/// Records the innovations created while reproducing one niche, without modifying the
+/// `GlobalInnovationCache` shared by all niches.
+///
+/// Known innovations are looked up in a snapshot of the shared cache. New innovations are
+/// provisional and only valid within the niche. `GlobalInnovationCache::commit` later creates
+/// the real innovations, so that niches can be reproduced in parallel and the innovation numbers
+/// only depend on the order in which niches are committed.
+
+#[derive(Debug)]
+pub struct StagedInnovationCache {
+    snapshot: Arc<GlobalInnovationCache>,
+    next_node_innovation: usize,
+    next_link_innovation: usize,
+    link_innovation_cache: BTreeMap<(NodeInnovation, NodeInnovation), LinkInnovation>,
+    split_node_innovation_cache: BTreeMap<LinkInnovation, NodeInnovation>,
+    log: Vec<StagedInnovation>,
+}
+
+impl StagedInnovationCache {
+    pub fn new(snapshot: Arc<GlobalInnovationCache>) -> Self {
+        StagedInnovationCache {
+            next_node_innovation: snapshot.node_innovation_counter.start,
+            next_link_innovation: snapshot.link_innovation_counter.start,
+            snapshot: snapshot,
+            link_innovation_cache: BTreeMap::new(),
+            split_node_innovation_cache: BTreeMap::new(),
+            log: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.log.is_empty()
+    }
+}
+
+impl GlobalCache for StagedInnovationCache {
+    fn get_or_create_link_innovation(&mut self,
+                                     source_node: NodeInnovation,
+                                     target_node: NodeInnovation)
+                                     -> LinkInnovation {
+        let key = (source_node, target_node);
+        if let Some(&cached_innovation) = self.snapshot.link_innovation_cache.get(&key) {
+            return cached_innovation;
+        }
+        if let Some(&staged_innovation) = self.link_innovation_cache.get(&key) {
+            return staged_innovation;
+        }
+        let new_innovation = LinkInnovation(self.next_link_innovation);
+        self.next_link_innovation += 1;
+        self.link_innovation_cache.insert(key, new_innovation);
+        self.log.push(StagedInnovation::Link(new_innovation, source_node, target_node));
+        new_innovation
+    }
+
+    fn create_node_innovation(&mut self) -> NodeInnovation {
+        let new_innovation = NodeInnovation(self.next_node_innovation);
+        self.next_node_innovation += 1;
+        self.log.push(StagedInnovation::Node(new_innovation, None));
+        new_innovation
+    }
+
+    fn get_or_create_split_node_innovation(&mut self,
+                                           split_link: LinkInnovation)
+                                           -> NodeInnovation {
+        if let Some(&cached_innovation) = self.snapshot
+                                              .split_node_innovation_cache
+                                              .get(&split_link) {
+            return cached_innovation;
+        }
+        if let Some(&staged_innovation) = self.split_node_innovation_cache.get(&split_link) {
+            return staged_innovation;
+        }
+        let new_innovation = NodeInnovation(self.next_node_innovation);
+        self.next_node_innovation += 1;
+        self.split_node_innovation_cache.insert(split_link, new_innovation);
+        self.log.push(StagedInnovation::Node(new_innovation, Some(split_link)));
+        new_innovation
+    }
+}
+
+/// Maps provisional innovations to their final innovations, as returned by
+/// `GlobalInnovationCache::commit`. Innovations not contained in the map are left unchanged.
+
+#[derive(Debug, Clone)]
+pub struct InnovationMap {
+    nodes: BTreeMap<NodeInnovation, NodeInnovation>,
+    links: BTreeMap<LinkInnovation, LinkInnovation>,
+}
+
+impl InnovationMap {
+    pub fn new() -> Self {
+        InnovationMap {
+            nodes: BTreeMap::new(),
+            links: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.links.is_empty()
+    }
+
+    pub fn node(&self, node: NodeInnovation) -> NodeInnovation {
+        self.nodes.get(&node).cloned().unwrap_or(node)
+    }
+
+    pub fn link(&self, link: LinkInnovation) -> LinkInnovation {
+        self.links.get(&link).cloned().unwrap_or(link)
+    }
+}
+
+/// Evolvable numeric parameters of a node gene.
+///
+/// A node computes `activate(gain * sum + bias)`, where `sum` is the weighted sum of it's
+/// incoming links. Parameters of nodes which do not accept incoming links have no effect. As
+/// feed-forward nodes have no state, they carry no time constant, see
+/// `recurrent_network::NodeParams` for that.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NodeParams {
+    pub bias: Weight,
+    pub gain: Weight,
+}
+
+impl NodeParams {
+    /// Creates neutral parameters, i.e. a bias of 0.0 and a gain of 1.0.
+
+    pub fn new() -> Self {
+        NodeParams {
+            bias: Weight(0.0),
+            gain: Weight(1.0),
+        }
+    }
+
+    /// The sum of the absolute differences of all parameters.
+
+    pub fn distance(&self, other: &NodeParams) -> f64 {
+        (self.bias.0 - other.bias.0).abs() + (self.gain.0 - other.gain.0).abs()
+    }
+}
+
+/// Parameters for the self-adaptation of mutation step sizes, as in evolution strategies.
+///
+/// Each time the link weights of a genome are mutated, it's step sizes are first multiplied by
+/// `exp(tau_global * N(0, 1))` (and per-link step sizes additionally by
+/// `exp(tau_local * N(0, 1))`), then the weights are offset by `step_size * N(0, 1)`.
+#[derive(Debug, Clone, Copy)]
+pub struct SelfAdaptation {
+    /// The step size of genomes which do not carry step sizes yet.
+    pub initial_sigma: f64,
+
+    /// Learning rate of the noise shared by all step sizes of a genome.
+    pub tau_global: f64,
+
+    /// Learning rate of the noise drawn for each per-link step size.
+    pub tau_local: f64,
+
+    /// Lower bound of all step sizes, which keeps them from collapsing to zero.
+    pub min_sigma: f64,
+
+    /// Carry one step size per link in addition to the genome wide step size.
+    pub per_link: bool,
+}
+
+/// The mutation step sizes carried by a genome.
+#[derive(Debug, Clone)]
+pub struct StepSizes {
+    /// The genome wide step size. Used for all links without their own step size.
+    pub sigma: f64,
+
+    /// Step sizes of individual links.
+    link_sigmas: BTreeMap<LinkInnovation, f64>,
+}
+
+impl StepSizes {
+    pub fn new(sigma: f64) -> Self {
+        StepSizes {
+            sigma: sigma,
+            link_sigmas: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the step size used for link `link_innovation`.
+
+    pub fn link_sigma(&self, link_innovation: LinkInnovation) -> f64 {
+        self.link_sigmas.get(&link_innovation).cloned().unwrap_or(self.sigma)
+    }
+
+    /// Recombines the step sizes of two parents by averaging them (intermediate recombination).
+    /// For links without their own step size, a parent contributes it's genome wide step size.
+    /// If only one parent carries step sizes, they are inherited unmodified.
+
+    pub fn recombine(left: Option<&StepSizes>, right: Option<&StepSizes>) -> Option<StepSizes> {
+        match (left, right) {
+            (Some(left), Some(right)) => {
+                let link_sigmas = left.link_sigmas
+                                      .keys()
+                                      .chain(right.link_sigmas.keys())
+                                      .map(|&li| {
+                                          (li, 0.5 * (left.link_sigma(li) + right.link_sigma(li)))
+                                      })
+                                      .collect();
+                Some(StepSizes {
+                    sigma: 0.5 * (left.sigma + right.sigma),
+                    link_sigmas: link_sigmas,
+                })
+            }
+            (Some(step_sizes), None) | (None, Some(step_sizes)) => Some(step_sizes.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// Mutates the step size of `link_innovation` (if `per_link`) and returns the perturbed
+    /// `weight`. `common_noise` is the noise shared by all step sizes of the genome.
+
+    fn perturb<R: Rng>(&mut self,
+                       link_innovation: LinkInnovation,
+                       weight: Weight,
+                       common_noise: f64,
+                       adaptation: &SelfAdaptation,
+                       link_weight_range: &WeightRange,
+                       rng: &mut R)
+                       -> Weight {
+        let normal = Normal::new(0.0, 1.0);
+        let sigma = if adaptation.per_link {
+            let noise = adaptation.tau_global * common_noise +
+                        adaptation.tau_local * normal.ind_sample(rng);
+            let sigma = (self.link_sigma(link_innovation) * noise.exp()).max(adaptation.min_sigma);
+            self.link_sigmas.insert(link_innovation, sigma);
+            sigma
+        } else {
+            self.sigma
+        };
+
+        link_weight_range.clip_weight(Weight(weight.0 + sigma * normal.ind_sample(rng)))
+    }
+}
+
+/// Determines which kind of dead structure `Genome::prune` removes.
+#[derive(Debug, Clone, Copy)]
+pub struct PruneOptions {
+    /// Remove hidden nodes which are unreachable from the inputs or have no path to any output,
+    /// together with all their links.
+    pub dead_nodes: bool,
+
+    /// Remove disabled links which have been disabled for more than `max_disabled_age`
+    /// generations (see `Genome::disabled_age`).
+    pub disabled_links: bool,
+
+    /// A link disabled while creating the offspring has an age of 0, so with 0 every link which
+    /// was already disabled in the parent is removed.
+    pub max_disabled_age: usize,
+}
+
+/// Describes the structure removed by `Genome::prune`.
+#[derive(Debug, Clone)]
+pub struct PruneReport {
+    pub removed_nodes: Vec<NodeInnovation>,
+    pub removed_links: Vec<LinkInnovation>,
+}
+
+impl PruneReport {
+    pub fn is_empty(&self) -> bool {
+        self.removed_nodes.is_empty() && self.removed_links.is_empty()
+    }
+}
+
+/// A violated invariant of a `Genome`, as reported by `Genome::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenomeViolation {
+    /// A node of the network has no entry in the node innovation map.
+    UnmappedNode(NodeInnovation),
+
+    /// An entry of the node innovation map refers to no or to a different node of the network.
+    DanglingMapEntry(NodeInnovation),
+
+    /// A node has no parameters.
+    MissingNodeParams(NodeInnovation),
+
+    /// Parameters exist for a node which is not part of the genome.
+    DanglingNodeParams(NodeInnovation),
+
+    /// The link innovation is used by more than one link.
+    DuplicateLinkInnovation(LinkInnovation),
+
+    /// The link is disabled but has no disabled age, or it has a disabled age but is not
+    /// disabled.
+    DisabledAgeMismatch(LinkInnovation),
+
+    /// The link originates from a node which does not accept outgoing links.
+    LinkFromNonAcceptingNode(LinkInnovation, NodeInnovation),
+
+    /// The link targets a node which does not accept incoming links.
+    LinkIntoNonAcceptingNode(LinkInnovation, NodeInnovation),
+
+    /// The links (active or not) form at least one cycle. Contains all nodes which lie on a
+    /// cycle or can only be reached through one.
+    Cycle(Vec<NodeInnovation>),
+}
+
+/// The structural and weight differences between an old and a new genome, as computed by
+/// `Genome::diff`. All lists are sorted by innovation number.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenomeDiff {
+    pub added_nodes: Vec<NodeInnovation>,
+    pub removed_nodes: Vec<NodeInnovation>,
+    pub added_links: Vec<LinkInnovation>,
+    pub removed_links: Vec<LinkInnovation>,
+    /// Links which are disabled in the old and active in the new genome.
+    pub enabled_links: Vec<LinkInnovation>,
+    /// Links which are active in the old and disabled in the new genome.
+    pub disabled_links: Vec<LinkInnovation>,
+    /// The weight change (new minus old) of each link present in both genomes, whose weight
+    /// differs.
+    pub weight_deltas: Vec<(LinkInnovation, f64)>,
+    /// Nodes present in both genomes, whose node type differs.
+    pub changed_node_types: Vec<NodeInnovation>,
+    /// The change (new minus old) of the bias and gain of each node present in both genomes,
+    /// whose parameters differ.
+    pub node_param_deltas: Vec<(NodeInnovation, NodeParams)>,
 }
 
+impl GenomeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty() && self.removed_nodes.is_empty() &&
+        self.added_links.is_empty() && self.removed_links.is_empty() &&
+        self.enabled_links.is_empty() && self.disabled_links.is_empty() &&
+        self.weight_deltas.is_empty() && self.changed_node_types.is_empty() &&
+        self.node_param_deltas.is_empty()
+    }
+}
 
 /// Genome representing a feed-forward (acyclic) network.
 ///
 /// Each node is uniquely identified by it's Innovation number. Each link is sorted according it's
 /// associated Innovation number.
 ///
-/// We have to keep both the `network` and the `node_innovation_map` in sync. That is, whenever we
-/// add or remove a node, we have to update both.
+/// We have to keep the `network`, the `node_innovation_map` and the `node_params` in sync. That
+/// is, whenever we add or remove a node, we have to update all of them. Likewise, `disabled_ages`
+/// has to be updated whenever a link is added, enabled or disabled.
 #[derive(Clone, Debug)]
 pub struct Genome<NT: NodeType> {
     /// Represents the acyclic feed forward network.
@@ -141,6 +726,17 @@ pub struct Genome<NT: NodeType> {
     /// Maps the external id (innovation number) which is globally allocated, to the internal
     /// network node index.
     node_innovation_map: BTreeMap<NodeInnovation, NodeIndex>,
+
+    /// The evolvable parameters of each node.
+    node_params: BTreeMap<NodeInnovation, NodeParams>,
+
+    /// The number of generations each disabled link has been disabled. Contains exactly the
+    /// disabled links.
+    disabled_ages: BTreeMap<LinkInnovation, usize>,
+
+    /// The self-adaptive mutation step sizes. `None` unless the genome was mutated using
+    /// `mutate_link_weights_self_adaptive` or inherited them.
+    step_sizes: Option<StepSizes>,
 }
 
 impl<NT: NodeType> Genotype for Genome<NT> {}
@@ -150,6 +746,9 @@ impl<NT: NodeType> Genome<NT> {
         Genome {
             network: Network::new(),
             node_innovation_map: BTreeMap::new(),
+            node_params: BTreeMap::new(),
+            disabled_ages: BTreeMap::new(),
+            step_sizes: None,
         }
     }
 
@@ -308,10 +907,15 @@ impl<NT: NodeType> Genome<NT> {
                                |&(kl, _), &(kr, _)| Ord::cmp(kl, kr),
                                |node_alignment| {
                                    match node_alignment {
-                                       Alignment::Match((_, &left_node_index),
-                                                        (_, &right_node_index)) => {
+                                       Alignment::Match((left_ni, &left_node_index),
+                                                        (right_ni, &right_node_index)) => {
                                            metric.node_metric.matching += 1;
 
+                                           // add up the distance of the node parameters
+                                           metric.node_metric.weight_distance +=
+                                               left_genome.node_params[left_ni]
+                                                   .distance(&right_genome.node_params[right_ni]);
+
                                            // Both nodes are topological identical. So the link innovations can
                                            // also match up.
                                            align_sorted_iterators(left_network.link_iter_for_node(left_node_index),
@@ -491,6 +1095,36 @@ impl<NT: NodeType> Genome<NT> {
                                                             weight,
                                                             AnyInnovation(link_innovation.0),
                                                             active);
+        if !active {
+            self.disabled_ages.insert(link_innovation, 0);
+        }
+    }
+
+    /// Takes over the disabled age of `link_innovation` from `parent`, after the link was copied
+    /// from there.
+
+    fn inherit_disabled_age(&mut self, parent: &Self, link_innovation: LinkInnovation) {
+        if let Some(&age) = parent.disabled_ages.get(&link_innovation) {
+            if self.disabled_ages.contains_key(&link_innovation) {
+                self.disabled_ages.insert(link_innovation, age);
+            }
+        }
+    }
+
+    /// Returns for how many generations the link has been disabled, or `None` if the link is
+    /// active or does not exist. A link disabled in the current generation has an age of 0.
+
+    pub fn disabled_age(&self, link_innovation: LinkInnovation) -> Option<usize> {
+        self.disabled_ages.get(&link_innovation).cloned()
+    }
+
+    /// Increases the age of all disabled links by one. Called once for each offspring by
+    /// `Mater::finish_offspring`, so the age counts the generations since the link was disabled.
+
+    pub fn age_disabled_links(&mut self) {
+        for age in self.disabled_ages.values_mut() {
+            *age += 1;
+        }
     }
 
     /// Check if the link is valid and if it would construct a cycle.
@@ -517,13 +1151,18 @@ impl<NT: NodeType> Genome<NT> {
         self.network.link_count()
     }
 
-    fn _add_node(&mut self, node_innovation: NodeInnovation, node_type: NT) -> NodeIndex {
+    fn _add_node(&mut self,
+                 node_innovation: NodeInnovation,
+                 node_type: NT,
+                 node_params: NodeParams)
+                 -> NodeIndex {
         if self.node_innovation_map.contains_key(&node_innovation) {
             panic!("Duplicate node_innovation");
         }
 
         let node_index = self.network.add_node(node_type, AnyInnovation(node_innovation.0));
         self.node_innovation_map.insert(node_innovation, node_index);
+        self.node_params.insert(node_innovation, node_params);
         return node_index;
     }
 
@@ -535,7 +1174,22 @@ impl<NT: NodeType> Genome<NT> {
     /// Panics if a node with the same innovation already exists in the genome.
 
     pub fn add_node(&mut self, node_innovation: NodeInnovation, node_type: NT) {
-        let _ = self._add_node(node_innovation, node_type);
+        let _ = self._add_node(node_innovation, node_type, NodeParams::new());
+    }
+
+    /// Same as `add_node`, but with explicit `node_params`.
+
+    pub fn add_node_with_params(&mut self,
+                                node_innovation: NodeInnovation,
+                                node_type: NT,
+                                node_params: NodeParams) {
+        let _ = self._add_node(node_innovation, node_type, node_params);
+    }
+
+    /// Returns the parameters of node `node_innovation` if it exists.
+
+    pub fn node_params(&self, node_innovation: NodeInnovation) -> Option<NodeParams> {
+        self.node_params.get(&node_innovation).cloned()
     }
 
     pub fn has_node(&self, node_innovation: NodeInnovation) -> bool {
@@ -549,16 +1203,35 @@ impl<NT: NodeType> Genome<NT> {
 
     /// Performs a crossover operation on the two genomes `left_genome` and `right_genome`,
     /// producing a new offspring genome.
+    ///
+    /// Blended link weights and node parameters are kept within `link_weight_range`,
+    /// `bias_range` and `gain_range`.
 
     pub fn crossover<R: Rng>(left_genome: &Self,
                              right_genome: &Self,
                              c: &ProbabilisticCrossover,
+                             link_weight_range: &WeightRange,
+                             bias_range: &WeightRange,
+                             gain_range: &WeightRange,
                              rng: &mut R)
                              -> Self {
         let mut offspring = Genome::new();
 
-        Genome::crossover_nodes(left_genome, right_genome, &mut offspring, c, rng);
-        let (_, _) = Genome::crossover_links(left_genome, right_genome, &mut offspring, c, rng);
+        Genome::crossover_nodes(left_genome,
+                                right_genome,
+                                &mut offspring,
+                                c,
+                                bias_range,
+                                gain_range,
+                                rng);
+        let (_, _) = Genome::crossover_links(left_genome,
+                                             right_genome,
+                                             &mut offspring,
+                                             c,
+                                             link_weight_range,
+                                             rng);
+        offspring.step_sizes = StepSizes::recombine(left_genome.step_sizes.as_ref(),
+                                                    right_genome.step_sizes.as_ref());
 
         return offspring;
     }
@@ -570,6 +1243,8 @@ impl<NT: NodeType> Genome<NT> {
                                right_genome: &Self,
                                offspring: &mut Self,
                                c: &ProbabilisticCrossover,
+                               bias_range: &WeightRange,
+                               gain_range: &WeightRange,
                                rng: &mut R) {
         let left_nodes = left_genome.node_innovation_map.iter();
         let right_nodes = right_genome.node_innovation_map.iter();
@@ -589,40 +1264,50 @@ impl<NT: NodeType> Genome<NT> {
 
                                            debug_assert!(ni_l == ni_r);
 
-                                           if c.prob_match_left.flip(rng) {
+                                           let left_params = left_genome.node_params[&ni_l];
+                                           let right_params = right_genome.node_params[&ni_r];
+
+                                           let (node_type, selected_params) = if c.prob_match_left.flip(rng) {
                                                // take from left
-                                               offspring.add_node(ni_l, left_network.node(left_node_index).node_type().clone());
+                                               (left_network.node(left_node_index).node_type().clone(), left_params)
                                            } else {
                                                // take from right
-                                               offspring.add_node(ni_r, right_network.node(right_node_index).node_type().clone());
-                                           }
+                                               (right_network.node(right_node_index).node_type().clone(), right_params)
+                                           };
+
+                                           let params = NodeParams {
+                                               bias: Weight(c.blend.blend(left_params.bias.0, right_params.bias.0, selected_params.bias.0, bias_range, rng)),
+                                               gain: Weight(c.blend.blend(left_params.gain.0, right_params.gain.0, selected_params.gain.0, gain_range, rng)),
+                                           };
+
+                                           offspring.add_node_with_params(ni_l, node_type, params);
                                        }
 
                                        Alignment::Disjoint((&ni_l, &left_node_index),
                                                            LeftOrRight::Left) => {
                                            if c.prob_disjoint_left.flip(rng) {
-                                               offspring.add_node(ni_l, left_network.node(left_node_index).node_type().clone());
+                                               offspring.add_node_with_params(ni_l, left_network.node(left_node_index).node_type().clone(), left_genome.node_params[&ni_l]);
                                            }
                                        }
 
                                        Alignment::Disjoint((&ni_r, &right_node_index),
                                                            LeftOrRight::Right) => {
                                            if c.prob_disjoint_right.flip(rng) {
-                                               offspring.add_node(ni_r, right_network.node(right_node_index).node_type().clone());
+                                               offspring.add_node_with_params(ni_r, right_network.node(right_node_index).node_type().clone(), right_genome.node_params[&ni_r]);
                                            }
                                        }
 
                                        Alignment::Excess((&ni_l, &left_node_index),
                                                          LeftOrRight::Left) => {
                                            if c.prob_excess_left.flip(rng) {
-                                               offspring.add_node(ni_l, left_network.node(left_node_index).node_type().clone());
+                                               offspring.add_node_with_params(ni_l, left_network.node(left_node_index).node_type().clone(), left_genome.node_params[&ni_l]);
                                            }
                                        }
 
                                        Alignment::Excess((&ni_r, &right_node_index),
                                                          LeftOrRight::Right) => {
                                            if c.prob_excess_right.flip(rng) {
-                                               offspring.add_node(ni_r, right_network.node(right_node_index).node_type().clone());
+                                               offspring.add_node_with_params(ni_r, right_network.node(right_node_index).node_type().clone(), right_genome.node_params[&ni_r]);
                                            }
                                        }
                                    }
@@ -635,6 +1320,7 @@ impl<NT: NodeType> Genome<NT> {
                                right_genome: &Self,
                                offspring: &mut Self,
                                c: &ProbabilisticCrossover,
+                               link_weight_range: &WeightRange,
                                rng: &mut R)
                                -> (usize, usize) {
 
@@ -656,19 +1342,28 @@ impl<NT: NodeType> Genome<NT> {
                     // Note that offspring already contains both the source and target node
                     // (assuming crossover_nodes() was called before) as also both of these
                     // nodes must exists in both parents.
-                    let link_ref = if c.prob_match_left.flip(rng) {
+                    let left_weight = left_link_ref.link().weight().0;
+                    let right_weight = right_link_ref.link().weight().0;
+                    let (link_ref, parent) = if c.prob_match_left.flip(rng) {
                         // take link weight from left
-                        left_link_ref
+                        (left_link_ref, left_genome)
                     } else {
                         // take link weight from right
-                        right_link_ref
+                        (right_link_ref, right_genome)
                     };
 
+                    let weight = c.blend.blend(left_weight,
+                                               right_weight,
+                                               link_ref.link().weight().0,
+                                               link_weight_range,
+                                               rng);
+
                     offspring.add_link_with_active(link_ref.external_source_node_id().into(),
                                                    link_ref.external_target_node_id().into(),
                                                    link_ref.external_link_id().into(),
-                                                   link_ref.link().weight(),
+                                                   Weight(weight),
                                                    link_ref.link().is_active());
+                    offspring.inherit_disabled_age(parent, link_ref.external_link_id().into());
 
                     total_links_added += 1;
                 }
@@ -704,27 +1399,38 @@ impl<NT: NodeType> Genome<NT> {
                         // Ignore. We already handled matching links in "Pass 1".
                     }
 
-                    Alignment::Disjoint(link_ref, _) | Alignment::Excess(link_ref, _) => {
+                    Alignment::Disjoint(link_ref, pos) | Alignment::Excess(link_ref, pos) => {
                         if prob.flip(rng) {
                             // Add nodes in case they do not exist.
                             // We take the nodeis from the genome the link belongs to.
 
+                            let parent = match pos {
+                                LeftOrRight::Left => left_genome,
+                                LeftOrRight::Right => right_genome,
+                            };
+
                             let mut nodes_added = 0;
                             let source_id = link_ref.external_source_node_id().into();
                             let target_id = link_ref.external_target_node_id().into();
 
                             if !offspring.has_node(source_id) {
                                 // add source node.
-                                offspring.add_node(source_id,
-                                                   link_ref.source_node().node_type().clone());
+                                offspring.add_node_with_params(source_id,
+                                                               link_ref.source_node()
+                                                                       .node_type()
+                                                                       .clone(),
+                                                               parent.node_params[&source_id]);
                                 nodes_added += 1;
                             }
 
                             if !offspring.has_node(target_id) {
                                 // add source node.
                                 // We take the node from the genome the link belongs to.
-                                offspring.add_node(target_id,
-                                                   link_ref.target_node().node_type().clone());
+                                offspring.add_node_with_params(target_id,
+                                                               link_ref.target_node()
+                                                                       .node_type()
+                                                                       .clone(),
+                                                               parent.node_params[&target_id]);
                                 nodes_added += 1;
                             }
 
@@ -755,6 +1461,9 @@ impl<NT: NodeType> Genome<NT> {
                                                                link_ref.external_link_id().into(),
                                                                link_ref.link().weight(),
                                                                link_ref.link().is_active());
+                                offspring.inherit_disabled_age(parent,
+                                                               link_ref.external_link_id()
+                                                                       .into());
                                 total_links_added += 1;
                             }
                         }
@@ -779,6 +1488,11 @@ impl<NT: NodeType> Genome<NT> {
             Some(idx) => {
                 let ok = self.network.enable_link_index(idx);
                 assert!(ok);
+                let link_innovation: LinkInnovation = self.network
+                                                          .link(idx)
+                                                          .external_link_id()
+                                                          .into();
+                self.disabled_ages.remove(&link_innovation);
                 true
             }
             None => false,
@@ -787,38 +1501,84 @@ impl<NT: NodeType> Genome<NT> {
 
     /// Mutate the genome by adding a random link which is valid and does not introduce a cycle.
     ///
+    /// The link is chosen among all pairs of nodes which are not connected by an active link. If
+    /// a disabled link between the chosen pair already exists, that link gene is re-enabled
+    /// instead, keeping it's innovation number and weight. Disabled links are taken into account
+    /// for the cycle check, so re-enabling one never introduces a cycle.
+    ///
     /// Return `true` if the genome was modified. Otherwise `false`.
 
     pub fn mutate_add_link<R, G>(&mut self, link_weight: Weight, cache: &mut G, rng: &mut R) -> bool
         where R: Rng,
               G: GlobalCache
     {
-        match self.network.find_random_unconnected_link_no_cycle(rng) {
-            Some((source_node_idx, target_node_idx)) => {
-                let ext_source_node_id: NodeInnovation = self.network
-                                                             .node(source_node_idx)
-                                                             .external_node_id()
-                                                             .into();
-                let ext_target_node_id: NodeInnovation = self.network
-                                                             .node(target_node_idx)
-                                                             .external_node_id()
-                                                             .into();
-
-                // Add new link to the offspring genome
-                self.network.add_link(source_node_idx,
-                                                target_node_idx,
-                                                link_weight,
-                                                AnyInnovation(cache.get_or_create_link_innovation(ext_source_node_id, ext_target_node_id).0));
-                return true;
-            }
-            None => {
-                return false;
+        let mut link_states: BTreeMap<(NodeInnovation, NodeInnovation), bool> = BTreeMap::new();
+        let mut successors: BTreeMap<NodeInnovation, Vec<NodeInnovation>> = BTreeMap::new();
+        self.network.each_link_ref(|link_ref| {
+            let source: NodeInnovation = link_ref.external_source_node_id().into();
+            let target: NodeInnovation = link_ref.external_target_node_id().into();
+            link_states.insert((source, target), link_ref.link().is_active());
+            successors.entry(source).or_insert_with(Vec::new).push(target);
+        });
+
+        let mut candidates = Vec::new();
+        for (&target, &target_idx) in self.node_innovation_map.iter() {
+            if !self.network.node(target_idx).node_type().accept_incoming_links() {
+                continue;
+            }
+            // a link into `target` closes a cycle iff it's source is reachable from `target`.
+            let reachable = Self::reachable_nodes(vec![target], &successors);
+            for (&source, &source_idx) in self.node_innovation_map.iter() {
+                if !self.network.node(source_idx).node_type().accept_outgoing_links() ||
+                   reachable.contains(&source) {
+                    continue;
+                }
+                match link_states.get(&(source, target)) {
+                    Some(&true) => {}
+                    Some(&false) => candidates.push((source, target, true)),
+                    None => candidates.push((source, target, false)),
+                }
             }
         }
-    }
 
-    /// Choose a random link. Split it in half creating a globally new node innovation!
-    /// XXX: activate if link is inactive?
+        let (source, target, disabled) = match rng.choose(&candidates) {
+            Some(&candidate) => candidate,
+            None => return false,
+        };
+
+        let source_node_idx = self.node_innovation_map[&source];
+        let target_node_idx = self.node_innovation_map[&target];
+
+        if disabled {
+            // Re-enable the disabled link gene
+            let link_idx = self.network
+                               .link_iter_for_node(source_node_idx)
+                               .find(|&(_, link)| link.target_node_index() == target_node_idx)
+                               .map(|(link_idx, link)| {
+                                   debug_assert!(!link.is_active());
+                                   link_idx
+                               })
+                               .unwrap();
+            let ok = self.network.enable_link_index(link_idx);
+            assert!(ok);
+            let link_innovation: LinkInnovation = self.network
+                                                     .link(link_idx)
+                                                     .external_link_id()
+                                                     .into();
+            self.disabled_ages.remove(&link_innovation);
+            return true;
+        }
+
+        // Add new link to the offspring genome
+        self.network.add_link(source_node_idx,
+                              target_node_idx,
+                              link_weight,
+                              AnyInnovation(cache.get_or_create_link_innovation(source, target).0));
+        true
+    }
+
+    /// Choose a random link. Split it in half creating a globally new node innovation!
+    /// XXX: activate if link is inactive?
     /// XXX: random_active_link() is O(n) vs. O(1) for random_link().
     ///
     /// Note that the new `node_type` should allow incoming and outgoing links! Otherwise
@@ -848,10 +1608,21 @@ impl<NT: NodeType> Genome<NT> {
 
         let _ok = self.network.disable_link_index(link_index);
         assert!(_ok);
-
-        // Create new node innovation and add it to the genome.
-        let new_node_innovation = cache.create_node_innovation();
-        let new_node_index = self._add_node(new_node_innovation, node_type);
+        let disabled_link: LinkInnovation = self.network
+                                                .link(link_index)
+                                                .external_link_id()
+                                                .into();
+        self.disabled_ages.insert(disabled_link, 0);
+
+        // Get the node innovation for splitting this link and add it to the genome. If the
+        // link was split before (and re-enabled later), the genome already contains that
+        // node, so we need a fresh innovation.
+        let split_link: LinkInnovation = self.network.link(link_index).external_link_id().into();
+        let mut new_node_innovation = cache.get_or_create_split_node_innovation(split_link);
+        if self.node_innovation_map.contains_key(&new_node_innovation) {
+            new_node_innovation = cache.create_node_innovation();
+        }
+        let new_node_index = self._add_node(new_node_innovation, node_type, NodeParams::new());
 
         // Add two new links connecting the three nodes. This cannot add a cycle!
         //
@@ -934,6 +1705,133 @@ impl<NT: NodeType> Genome<NT> {
         return modifications;
     }
 
+    /// Returns the self-adaptive mutation step sizes of the genome.
+
+    pub fn step_sizes(&self) -> Option<&StepSizes> {
+        self.step_sizes.as_ref()
+    }
+
+    pub fn set_step_sizes(&mut self, step_sizes: Option<StepSizes>) {
+        self.step_sizes = step_sizes;
+    }
+
+    /// Like `mutate_link_weights_uniformly`, but uses the step sizes carried by the genome
+    /// instead of a global perturbance method. The step sizes are mutated log-normally before
+    /// they are applied (see `SelfAdaptation`). Genomes without step sizes start with
+    /// `adaptation.initial_sigma`.
+    ///
+    /// Returns the number of modifications
+
+    pub fn mutate_link_weights_self_adaptive<R: Rng>(&mut self,
+                                                     mutate_prob: Prob,
+                                                     adaptation: &SelfAdaptation,
+                                                     link_weight_range: &WeightRange,
+                                                     rng: &mut R)
+                                                     -> usize {
+        // Our network does not contain any links. Abort.
+        if self.network.link_count() == 0 {
+            return 0;
+        }
+
+        let mut step_sizes = self.step_sizes
+                                 .take()
+                                 .unwrap_or_else(|| StepSizes::new(adaptation.initial_sigma));
+
+        let common_noise = Normal::new(0.0, 1.0).ind_sample(rng);
+
+        if adaptation.per_link {
+            // forget the step sizes of links which no longer exist. Links without their own step
+            // size start with the genome wide step size before it is mutated, as `perturb`
+            // applies the common noise to them anyway.
+            let mut link_sigmas = BTreeMap::new();
+            self.network.each_link_ref(|link_ref| {
+                let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+                link_sigmas.insert(link_innovation, step_sizes.link_sigma(link_innovation));
+            });
+            step_sizes.link_sigmas = link_sigmas;
+        }
+
+        step_sizes.sigma = (step_sizes.sigma * (adaptation.tau_global * common_noise).exp())
+                               .max(adaptation.min_sigma);
+
+        let mut modifications = 0;
+
+        self.network.each_link_mut(|link| {
+            if mutate_prob.flip(rng) {
+                let new_weight = step_sizes.perturb(link.external_link_id().into(),
+                                                    link.weight(),
+                                                    common_noise,
+                                                    adaptation,
+                                                    link_weight_range,
+                                                    rng);
+                link.set_weight(new_weight);
+                modifications += 1;
+            }
+        });
+
+        if modifications == 0 {
+            // Make at least one change to a randomly selected link.
+            let link_idx = self.network.random_link_index(rng).unwrap();
+            let link = self.network.link_mut(link_idx);
+            let new_weight = step_sizes.perturb(link.external_link_id().into(),
+                                                link.weight(),
+                                                common_noise,
+                                                adaptation,
+                                                link_weight_range,
+                                                rng);
+            link.set_weight(new_weight);
+            modifications += 1;
+        }
+
+        self.step_sizes = Some(step_sizes);
+
+        assert!(modifications > 0);
+        return modifications;
+    }
+
+    /// Uniformly modify the parameters of node genes, each with a probability of `mutate_prob`.
+    /// Only nodes which accept incoming links are considered, as the parameters of all other
+    /// nodes have no effect. Like `mutate_link_weights_uniformly`, at least one node is modified
+    /// (if there is any such node).
+    ///
+    /// Returns the number of modifications
+
+    pub fn mutate_node_params_uniformly<R: Rng>(&mut self,
+                                                mutate_prob: Prob,
+                                                perturbance: &WeightPerturbanceMethod,
+                                                bias_range: &WeightRange,
+                                                gain_range: &WeightRange,
+                                                rng: &mut R)
+                                                -> usize {
+        let mut candidates = Vec::new();
+        for (&ni, &node_index) in self.node_innovation_map.iter() {
+            if self.network.node(node_index).node_type().accept_incoming_links() {
+                candidates.push(ni);
+            }
+        }
+
+        if candidates.is_empty() {
+            return 0;
+        }
+
+        let mut selected: Vec<NodeInnovation> = candidates.iter()
+                                                          .cloned()
+                                                          .filter(|_| mutate_prob.flip(rng))
+                                                          .collect();
+        if selected.is_empty() {
+            // Make at least one change to a randomly selected node.
+            selected.push(*rng.choose(&candidates).unwrap());
+        }
+
+        for ni in selected.iter() {
+            let params = self.node_params.get_mut(ni).unwrap();
+            params.bias = perturbance.perturb(params.bias, bias_range, rng);
+            params.gain = perturbance.perturb(params.gain, gain_range, rng);
+        }
+
+        return selected.len();
+    }
+
     /// Mutate the genome by removing a random link.
     ///
     /// Return `true` if the genome was modified. Otherwise `false`.
@@ -947,32 +1845,501 @@ impl<NT: NodeType> Genome<NT> {
             None => false,
         }
     }
+
+    /// Returns a copy of the genome, where the type of each node is determined by
+    /// `map_node(node_innovation, node_type)`. If this returns `None`, the node is left out
+    /// together with all it's links.
+    ///
+    /// Note that the new node types must accept the links of the original node.
+
+    fn rebuild<F>(&self, map_node: F) -> Self
+        where F: FnMut(NodeInnovation, &NT) -> Option<NT>
+    {
+        self.rebuild_filtered(map_node, |_, _| true)
+    }
+
+    /// Like `rebuild`, but additionally leaves out all links for which
+    /// `keep_link(link_innovation, active)` returns `false`.
+
+    fn rebuild_filtered<F, L>(&self, mut map_node: F, mut keep_link: L) -> Self
+        where F: FnMut(NodeInnovation, &NT) -> Option<NT>,
+              L: FnMut(LinkInnovation, bool) -> bool
+    {
+        let mut genome = Genome::new();
+        genome.step_sizes = self.step_sizes.clone();
+
+        for (&ni, &node_index) in self.node_innovation_map.iter() {
+            if let Some(node_type) = map_node(ni, self.network.node(node_index).node_type()) {
+                genome.add_node_with_params(ni, node_type, self.node_params[&ni]);
+            }
+        }
+
+        self.network.each_link_ref(|link_ref| {
+            let source_node: NodeInnovation = link_ref.external_source_node_id().into();
+            let target_node: NodeInnovation = link_ref.external_target_node_id().into();
+            let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+            if genome.has_node(source_node) && genome.has_node(target_node) &&
+               keep_link(link_innovation, link_ref.link().is_active()) {
+                genome.add_link_with_active(source_node,
+                                            target_node,
+                                            link_innovation,
+                                            link_ref.link().weight(),
+                                            link_ref.link().is_active());
+                genome.inherit_disabled_age(self, link_innovation);
+            }
+        });
+
+        genome
+    }
+
+    /// Returns `true` if any node or link innovation of the genome is changed by `map`.
+
+    fn is_renumbered_by(&self, map: &InnovationMap) -> bool {
+        if self.node_innovation_map.keys().any(|ni| map.nodes.contains_key(ni)) {
+            return true;
+        }
+        let mut renumbered = false;
+        self.network.each_link_ref(|link_ref| {
+            let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+            if map.links.contains_key(&link_innovation) {
+                renumbered = true;
+            }
+        });
+        renumbered
+    }
+
+    /// Returns a copy of the genome with all node and link innovations replaced according to
+    /// `map`.
+
+    pub fn renumber(&self, map: &InnovationMap) -> Self {
+        let mut genome = Genome::new();
+        genome.step_sizes = self.step_sizes.as_ref().map(|steps| {
+            StepSizes {
+                sigma: steps.sigma,
+                link_sigmas: steps.link_sigmas
+                                  .iter()
+                                  .map(|(&link, &sigma)| (map.link(link), sigma))
+                                  .collect(),
+            }
+        });
+
+        for (&ni, &node_index) in self.node_innovation_map.iter() {
+            genome.add_node_with_params(map.node(ni),
+                                        self.network.node(node_index).node_type().clone(),
+                                        self.node_params[&ni]);
+        }
+
+        self.network.each_link_ref(|link_ref| {
+            let source_node: NodeInnovation = link_ref.external_source_node_id().into();
+            let target_node: NodeInnovation = link_ref.external_target_node_id().into();
+            let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+            genome.add_link_with_active(map.node(source_node),
+                                        map.node(target_node),
+                                        map.link(link_innovation),
+                                        link_ref.link().weight(),
+                                        link_ref.link().is_active());
+        });
+        for (&link_innovation, &age) in self.disabled_ages.iter() {
+            genome.disabled_ages.insert(map.link(link_innovation), age);
+        }
+
+        genome
+    }
+
+    /// Returns all hidden nodes, i.e. nodes which accept both incoming and outgoing links.
+
+    fn hidden_nodes(&self) -> Vec<NodeInnovation> {
+        let network = &self.network;
+        self.node_innovation_map
+            .iter()
+            .filter(|&(_, &node_index)| {
+                let node_type = network.node(node_index).node_type();
+                node_type.accept_incoming_links() && node_type.accept_outgoing_links()
+            })
+            .map(|(&ni, _)| ni)
+            .collect()
+    }
+
+    /// Mutate the genome by removing a random hidden node (a node which accepts both incoming and
+    /// outgoing links) together with all it's links.
+    ///
+    /// If `bridge_links` is `true`, each source of an active incoming link is connected to each
+    /// target of an active outgoing link of the removed node, unless such a link already exists
+    /// or would introduce a cycle. The weight of a bridging link is the product of both weights,
+    /// clipped to `link_weight_range`.
+    ///
+    /// Return `true` if the genome was modified. Otherwise `false`.
+    ///
+    /// # Complexity
+    ///
+    /// The genome is rebuilt without the node, which is O(n + m).
+
+    pub fn mutate_delete_node<R, G>(&mut self,
+                                    bridge_links: bool,
+                                    link_weight_range: &WeightRange,
+                                    cache: &mut G,
+                                    rng: &mut R)
+                                    -> bool
+        where R: Rng,
+              G: GlobalCache
+    {
+        let hidden_nodes = self.hidden_nodes();
+
+        let removed_node = match rng.choose(&hidden_nodes) {
+            Some(&ni) => ni,
+            None => return false,
+        };
+
+        let mut incoming = Vec::new();
+        let mut outgoing = Vec::new();
+        self.visit_active_links(|source_node, target_node, weight| {
+            if target_node == removed_node {
+                incoming.push((source_node, weight));
+            }
+            if source_node == removed_node {
+                outgoing.push((target_node, weight));
+            }
+        });
+
+        let mut genome = self.rebuild(|ni, node_type| {
+            if ni == removed_node {
+                None
+            } else {
+                Some(node_type.clone())
+            }
+        });
+
+        if bridge_links {
+            for &(source_node, in_weight) in incoming.iter() {
+                for &(target_node, out_weight) in outgoing.iter() {
+                    if genome.valid_link_no_cycle(source_node, target_node) {
+                        let weight = link_weight_range.clip_weight(Weight(in_weight.0 *
+                                                                          out_weight.0));
+                        let link_innovation =
+                            cache.get_or_create_link_innovation(source_node, target_node);
+                        genome.add_link(source_node, target_node, link_innovation, weight);
+                    }
+                }
+            }
+        }
+
+        *self = genome;
+        return true;
+    }
+
+    /// Returns all hidden nodes which do not contribute to the output of the network. These are
+    /// the nodes that cannot be reached from any input node (a node which does not accept
+    /// incoming links) or which have no path to any output node (a node which does not accept
+    /// outgoing links). Only active links are followed.
+
+    pub fn dead_nodes(&self) -> Vec<NodeInnovation> {
+        let mut successors: BTreeMap<NodeInnovation, Vec<NodeInnovation>> = BTreeMap::new();
+        let mut predecessors: BTreeMap<NodeInnovation, Vec<NodeInnovation>> = BTreeMap::new();
+        self.visit_active_links(|source, target, _| {
+            successors.entry(source).or_insert_with(Vec::new).push(target);
+            predecessors.entry(target).or_insert_with(Vec::new).push(source);
+        });
+
+        let mut sources = Vec::new();
+        let mut sinks = Vec::new();
+        self.visit_nodes(|ni, node_type| {
+            if !node_type.accept_incoming_links() {
+                sources.push(ni);
+            }
+            if !node_type.accept_outgoing_links() {
+                sinks.push(ni);
+            }
+        });
+
+        let reachable = Self::reachable_nodes(sources, &successors);
+        let productive = Self::reachable_nodes(sinks, &predecessors);
+
+        self.hidden_nodes()
+            .into_iter()
+            .filter(|ni| !(reachable.contains(ni) && productive.contains(ni)))
+            .collect()
+    }
+
+    /// Returns all nodes reachable from `start_nodes` following the `edges`.
+
+    fn reachable_nodes(start_nodes: Vec<NodeInnovation>,
+                       edges: &BTreeMap<NodeInnovation, Vec<NodeInnovation>>)
+                       -> BTreeSet<NodeInnovation> {
+        let mut visited = BTreeSet::new();
+        let mut queue: VecDeque<NodeInnovation> = start_nodes.into_iter().collect();
+
+        while let Some(ni) = queue.pop_front() {
+            if !visited.insert(ni) {
+                continue;
+            }
+            if let Some(next_nodes) = edges.get(&ni) {
+                queue.extend(next_nodes.iter().cloned());
+            }
+        }
+
+        visited
+    }
+
+    /// Returns all disabled links.
+
+    pub fn disabled_links(&self) -> Vec<LinkInnovation> {
+        let mut disabled_links = Vec::new();
+        self.network.each_link_ref(|link_ref| {
+            if !link_ref.link().is_active() {
+                disabled_links.push(link_ref.external_link_id().into());
+            }
+        });
+        disabled_links
+    }
+
+    /// Removes the dead structure selected by `options` and reports what was removed. The
+    /// links of removed nodes are reported as removed links as well.
+    ///
+    /// A single call is sufficient, as removing dead nodes never turns other nodes into dead
+    /// nodes.
+    ///
+    /// # Complexity
+    ///
+    /// If anything is removed, the genome is rebuilt, which is O(n + m).
+
+    pub fn prune(&mut self, options: &PruneOptions) -> PruneReport {
+        let removed_nodes = if options.dead_nodes {
+            self.dead_nodes()
+        } else {
+            Vec::new()
+        };
+
+        let mut removed_links = Vec::new();
+        let disabled_ages = &self.disabled_ages;
+        self.network.each_link_ref(|link_ref| {
+            let source_node: NodeInnovation = link_ref.external_source_node_id().into();
+            let target_node: NodeInnovation = link_ref.external_target_node_id().into();
+            let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+            let expired = match disabled_ages.get(&link_innovation) {
+                Some(&age) => options.disabled_links && age > options.max_disabled_age,
+                None => false,
+            };
+            if removed_nodes.contains(&source_node) || removed_nodes.contains(&target_node) ||
+               expired {
+                removed_links.push(link_innovation);
+            }
+        });
+
+        let report = PruneReport {
+            removed_nodes: removed_nodes,
+            removed_links: removed_links,
+        };
+
+        if !report.is_empty() {
+            let map_node = |ni: NodeInnovation, node_type: &NT| {
+                if report.removed_nodes.contains(&ni) {
+                    None
+                } else {
+                    Some(node_type.clone())
+                }
+            };
+            let keep_link = |li: LinkInnovation, _active: bool| {
+                !report.removed_links.contains(&li)
+            };
+            let genome = self.rebuild_filtered(map_node, keep_link);
+            *self = genome;
+        }
+
+        report
+    }
+
+    /// Checks the invariants of the genome and returns all violations found.
+    ///
+    /// These are:
+    ///
+    /// * The `node_innovation_map` and the `node_params` contain exactly the nodes of the
+    ///   `network`.
+    /// * Each link has a unique link innovation.
+    /// * The `disabled_ages` contain exactly the disabled links.
+    /// * Each link originates from a node accepting outgoing links and targets a node accepting
+    ///   incoming links.
+    /// * The links (including disabled ones) do not form a cycle.
+    ///
+    /// # Complexity
+    ///
+    /// O(n log n + m log m) where `n` is the number of nodes and `m` the number of links.
+
+    pub fn validate(&self) -> Result<(), Vec<GenomeViolation>> {
+        let mut violations = Vec::new();
+
+        let mut network_nodes: BTreeMap<NodeInnovation, NodeIndex> = BTreeMap::new();
+        self.network.each_node_with_index(|node, node_index| {
+            network_nodes.insert(node.external_node_id().into(), node_index);
+        });
+
+        for (&ni, node_index) in network_nodes.iter() {
+            if self.node_innovation_map.get(&ni) != Some(node_index) {
+                violations.push(GenomeViolation::UnmappedNode(ni));
+            }
+        }
+
+        for (&ni, node_index) in self.node_innovation_map.iter() {
+            if network_nodes.get(&ni) != Some(node_index) {
+                violations.push(GenomeViolation::DanglingMapEntry(ni));
+            }
+        }
+
+        for &ni in network_nodes.keys() {
+            if !self.node_params.contains_key(&ni) {
+                violations.push(GenomeViolation::MissingNodeParams(ni));
+            }
+        }
+
+        for &ni in self.node_params.keys() {
+            if !network_nodes.contains_key(&ni) {
+                violations.push(GenomeViolation::DanglingNodeParams(ni));
+            }
+        }
+
+        let mut link_innovations = BTreeSet::new();
+        let mut disabled_links = BTreeSet::new();
+        let mut links = Vec::new();
+
+        self.network.each_link_ref(|link_ref| {
+            let source_node: NodeInnovation = link_ref.external_source_node_id().into();
+            let target_node: NodeInnovation = link_ref.external_target_node_id().into();
+            let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+
+            if !link_innovations.insert(link_innovation) {
+                violations.push(GenomeViolation::DuplicateLinkInnovation(link_innovation));
+            }
+            if !link_ref.link().is_active() {
+                disabled_links.insert(link_innovation);
+            }
+            if !link_ref.source_node().node_type().accept_outgoing_links() {
+                violations.push(GenomeViolation::LinkFromNonAcceptingNode(link_innovation,
+                                                                          source_node));
+            }
+            if !link_ref.target_node().node_type().accept_incoming_links() {
+                violations.push(GenomeViolation::LinkIntoNonAcceptingNode(link_innovation,
+                                                                          target_node));
+            }
+
+            links.push((source_node, target_node));
+        });
+
+        let aged_links: BTreeSet<LinkInnovation> = self.disabled_ages.keys().cloned().collect();
+        for &li in disabled_links.symmetric_difference(&aged_links) {
+            violations.push(GenomeViolation::DisabledAgeMismatch(li));
+        }
+
+        let cyclic_nodes = Self::cyclic_nodes(&links);
+        if !cyclic_nodes.is_empty() {
+            violations.push(GenomeViolation::Cycle(cyclic_nodes));
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Returns all nodes which lie on a cycle formed by `links`, or can only be reached through
+    /// one, sorted by innovation number.
+    ///
+    /// Kept apart from `validate`, as a cyclic genome can't be built through the genome or the
+    /// network API, so the check can only be tested on it's own.
+
+    fn cyclic_nodes(links: &[(NodeInnovation, NodeInnovation)]) -> Vec<NodeInnovation> {
+        let mut successors: BTreeMap<NodeInnovation, Vec<NodeInnovation>> = BTreeMap::new();
+        let mut in_degree: BTreeMap<NodeInnovation, usize> = BTreeMap::new();
+        for &(source_node, target_node) in links.iter() {
+            successors.entry(source_node).or_insert_with(Vec::new).push(target_node);
+            in_degree.entry(source_node).or_insert(0);
+            *in_degree.entry(target_node).or_insert(0) += 1;
+        }
+
+        // Kahn's algorithm. Nodes which are never removed lie on or behind a cycle.
+        let mut queue: VecDeque<NodeInnovation> = in_degree.iter()
+            .filter(|&(_, &degree)| degree == 0)
+            .map(|(&ni, _)| ni)
+            .collect();
+        while let Some(ni) = queue.pop_front() {
+            in_degree.remove(&ni);
+            if let Some(targets) = successors.get(&ni) {
+                for target in targets.iter() {
+                    let degree = in_degree.get_mut(target).unwrap();
+                    *degree -= 1;
+                    if *degree == 0 {
+                        queue.push_back(*target);
+                    }
+                }
+            }
+        }
+
+        in_degree.keys().cloned().collect()
+    }
 }
 
-/// This is used to weight a link AlignmentMetric.
+/// Weights the link and node AlignmentMetrics of two genomes to calculate their distance.
+///
+/// The link part uses `excess`, `disjoint` and `weight`. The node part uses `node_excess`,
+/// `node_disjoint`, `node_type` (for matching nodes of different type) and `node_params` (the
+/// mean parameter distance of matching nodes).
 #[derive(Debug)]
 pub struct GenomeDistance {
     pub excess: f64,
     pub disjoint: f64,
     pub weight: f64,
+    pub node_params: f64,
+    pub node_excess: f64,
+    pub node_disjoint: f64,
+    pub node_type: f64,
 }
 
-impl<NT: NodeType> Distance<Genome<NT>> for GenomeDistance {
-    fn distance(&self, genome_left: &Genome<NT>, genome_right: &Genome<NT>) -> f64 {
-        let m = Genome::combined_alignment_metric(genome_left, genome_right).link_metric;
+impl GenomeDistance {
+    /// The distance contributed by the link genes.
 
+    pub fn link_distance(&self, m: &AlignmentMetric) -> f64 {
         if m.max_len == 0 {
             return 0.0;
         }
 
-        self.excess * (m.excess as f64) / (m.max_len as f64) +
-        self.disjoint * (m.disjoint as f64) / (m.max_len as f64) +
-        self.weight *
-        if m.matching > 0 {
+        let mean_weight_distance = if m.matching > 0 {
             m.weight_distance / (m.matching as f64)
         } else {
             0.0
+        };
+
+        self.excess * (m.excess as f64) / (m.max_len as f64) +
+        self.disjoint * (m.disjoint as f64) / (m.max_len as f64) +
+        self.weight * mean_weight_distance
+    }
+
+    /// The distance contributed by the node genes. `node_type_mismatches` is the number of
+    /// matching nodes which differ in their node type.
+
+    pub fn node_distance(&self, n: &AlignmentMetric, node_type_mismatches: usize) -> f64 {
+        if n.max_len == 0 {
+            return 0.0;
         }
+
+        let (mean_params_distance, mismatch_ratio) = if n.matching > 0 {
+            (n.weight_distance / (n.matching as f64),
+             (node_type_mismatches as f64) / (n.matching as f64))
+        } else {
+            (0.0, 0.0)
+        };
+
+        self.node_excess * (n.excess as f64) / (n.max_len as f64) +
+        self.node_disjoint * (n.disjoint as f64) / (n.max_len as f64) +
+        self.node_type * mismatch_ratio + self.node_params * mean_params_distance
+    }
+}
+
+impl<NT: NodeType + PartialEq> Distance<Genome<NT>> for GenomeDistance {
+    fn distance(&self, genome_left: &Genome<NT>, genome_right: &Genome<NT>) -> f64 {
+        let metric = Genome::combined_alignment_metric(genome_left, genome_right);
+        let node_type_mismatches = Genome::node_type_mismatches(genome_left, genome_right);
+
+        self.link_distance(&metric.link_metric) +
+        self.node_distance(&metric.node_metric, node_type_mismatches)
     }
 }
 
@@ -983,6 +2350,23 @@ pub trait ElementStrategy<NT: NodeType>
     fn link_weight_range(&self) -> WeightRange;
     fn full_link_weight(&self) -> Weight;
     fn random_node_type<R: Rng>(&self, rng: &mut R) -> NT;
+
+    /// The range of the node bias. Defaults to the `link_weight_range`.
+    fn node_bias_range(&self) -> WeightRange {
+        self.link_weight_range()
+    }
+
+    /// The range of the node gain. Defaults to [0, 2], which contains the neutral gain of 1.0
+    /// and keeps the gain from changing it's sign.
+    fn node_gain_range(&self) -> WeightRange {
+        WeightRange::new(2.0, 0.0)
+    }
+
+    /// The range of the node time constant, which is only used by recurrent genomes. Defaults
+    /// to [1, 10]. A time constant below 1.0 would overshoot in each time step.
+    fn node_time_constant_range(&self) -> WeightRange {
+        WeightRange::new(10.0, 1.0)
+    }
 }
 
 /// Implementation for mating.
@@ -997,17 +2381,85 @@ pub struct Mater<'a, N, S, C>
     pub p_crossover_detail: ProbabilisticCrossover,
     pub p_mutate_element: Prob,
     pub weight_perturbance: WeightPerturbanceMethod,
+    // if set, weights are mutated using the step sizes carried by each genome instead of
+    // `weight_perturbance`
+    pub self_adaptation: Option<SelfAdaptation>,
     pub mutate_weights: MutateMethodWeighting,
-    pub global_cache: &'a mut C,
+    pub mutation_schedule: MutationSchedule,
+    pub bridge_deleted_nodes: bool,
+    // remove dead structure from each offspring
+    pub prune_offspring: Option<PruneOptions>,
+    // discard an offspring which violates the invariants of a genome
+    pub validate_offspring: bool,
+    // usually a `&mut GlobalInnovationCache`, or a `&ConcurrentInnovationCache` to share the
+    // innovations between `Mater`s on different threads.
+    pub global_cache: C,
     pub element_strategy: &'a S,
     pub _n: PhantomData<N>,
 }
 
+/// Clones the settings. Both `Mater`s share the same `element_strategy`. Whether they share the
+/// same innovation cache depends on how `C` is cloned.
+
+impl<'a, N, S, C> Clone for Mater<'a, N, S, C>
+    where N: NodeType + 'a,
+          S: ElementStrategy<N> + 'a,
+          C: GlobalCache + Clone + 'a
+{
+    fn clone(&self) -> Self {
+        self.with_global_cache(self.global_cache.clone())
+    }
+}
+
 impl<'a, N, S, C> Mater<'a, N, S, C>
     where N: NodeType + 'a,
           S: ElementStrategy<N> + 'a,
           C: GlobalCache + 'a
 {
+    /// Returns a `Mater` with the same settings as `self`, using `global_cache`.
+
+    pub fn with_global_cache<D>(&self, global_cache: D) -> Mater<'a, N, S, D>
+        where D: GlobalCache + 'a
+    {
+        Mater {
+            p_crossover: self.p_crossover,
+            p_crossover_detail: self.p_crossover_detail,
+            p_mutate_element: self.p_mutate_element,
+            weight_perturbance: self.weight_perturbance,
+            self_adaptation: self.self_adaptation,
+            mutate_weights: self.mutate_weights,
+            mutation_schedule: self.mutation_schedule,
+            bridge_deleted_nodes: self.bridge_deleted_nodes,
+            prune_offspring: self.prune_offspring,
+            validate_offspring: self.validate_offspring,
+            global_cache: global_cache,
+            element_strategy: self.element_strategy,
+            _n: PhantomData,
+        }
+    }
+}
+
+impl<'a, N, S, C> GenomeMater<Genome<N>> for Mater<'a, N, S, C>
+    where N: NodeType + PartialEq + 'a,
+          S: ElementStrategy<N> + 'a,
+          C: GlobalCache + 'a
+{
+    fn p_crossover(&self) -> Prob {
+        self.p_crossover
+    }
+
+    fn p_crossover_detail(&self) -> ProbabilisticCrossover {
+        self.p_crossover_detail
+    }
+
+    fn mutate_weights(&self) -> MutateMethodWeighting {
+        self.mutate_weights
+    }
+
+    fn mutation_schedule(&self) -> MutationSchedule {
+        self.mutation_schedule
+    }
+
     fn mutate<R: Rng>(&mut self,
                       offspring: &mut Genome<N>,
                       mutate_method: MutateMethod,
@@ -1015,86 +2467,944 @@ impl<'a, N, S, C> Mater<'a, N, S, C>
                       -> bool {
         match mutate_method {
             MutateMethod::ModifyWeight => {
+                let link_weight_range = self.element_strategy.link_weight_range();
+                let modifications = match self.self_adaptation {
+                    Some(ref adaptation) => {
+                        offspring.mutate_link_weights_self_adaptive(self.p_mutate_element,
+                                                                    adaptation,
+                                                                    &link_weight_range,
+                                                                    rng)
+                    }
+                    None => {
+                        offspring.mutate_link_weights_uniformly(self.p_mutate_element,
+                                                                &self.weight_perturbance,
+                                                                &link_weight_range,
+                                                                rng)
+                    }
+                };
+
+                modifications > 0
+            }
+            MutateMethod::AddConnection => {
+                let link_weight = self.element_strategy
+                                      .link_weight_range()
+                                      .random_weight(rng);
+                offspring.mutate_add_link(link_weight, &mut self.global_cache, rng)
+            }
+            MutateMethod::EnableConnection => offspring.mutate_enable_link(rng),
+            MutateMethod::DeleteConnection => offspring.mutate_delete_link(rng),
+            MutateMethod::ChangeNodeType => {
+                let node_type = self.element_strategy.random_node_type(rng);
+                offspring.mutate_change_node_type(node_type, rng)
+            }
+            MutateMethod::DeleteNode => {
+                offspring.mutate_delete_node(self.bridge_deleted_nodes,
+                                             &self.element_strategy.link_weight_range(),
+                                             &mut self.global_cache,
+                                             rng)
+            }
+            MutateMethod::AddNode => {
+                let second_link_weight = self.element_strategy.full_link_weight();
+                let node_type = self.element_strategy.random_node_type(rng);
+                offspring.mutate_add_node(node_type, second_link_weight, &mut self.global_cache, rng)
+            }
+            MutateMethod::ModifyNodeParams => {
                 let modifications =
-                    offspring.mutate_link_weights_uniformly(self.p_mutate_element,
-                                                            &self.weight_perturbance,
-                                                            &self.element_strategy
-                                                                 .link_weight_range(),
-                                                            rng);
+                    offspring.mutate_node_params_uniformly(self.p_mutate_element,
+                                                           &self.weight_perturbance,
+                                                           &self.element_strategy
+                                                                .node_bias_range(),
+                                                           &self.element_strategy
+                                                                .node_gain_range(),
+                                                           rng);
+
+                modifications > 0
+            }
+        }
+    }
+
+    fn crossover<R: Rng>(&mut self,
+                         parent_left: &Genome<N>,
+                         parent_right: &Genome<N>,
+                         crossover: &ProbabilisticCrossover,
+                         rng: &mut R)
+                         -> Genome<N> {
+        Genome::crossover(parent_left,
+                          parent_right,
+                          crossover,
+                          &self.element_strategy.link_weight_range(),
+                          &self.element_strategy.node_bias_range(),
+                          &self.element_strategy.node_gain_range(),
+                          rng)
+    }
+
+    fn finish_offspring(&mut self, offspring: &mut Genome<N>) -> bool {
+        offspring.age_disabled_links();
+
+        if let Some(ref options) = self.prune_offspring {
+            let report = offspring.prune(options);
+            if !report.is_empty() {
+                debug!("pruned offspring: {:?}", report);
+            }
+        }
+
+        if self.validate_offspring {
+            if let Err(violations) = offspring.validate() {
+                warn!("discarding invalid offspring: {:?}", violations);
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl<'a, N, S, C> Mate<Genome<N>> for Mater<'a, N, S, C>
+    where N: NodeType + PartialEq + 'a,
+          S: ElementStrategy<N> + 'a,
+          C: GlobalCache + 'a
+{
+    fn mate<R: Rng>(&mut self,
+                    parent_left: &Genome<N>,
+                    parent_right: &Genome<N>,
+                    fitness_left: Fitness,
+                    fitness_right: Fitness,
+                    prefer_mutate: bool,
+                    rng: &mut R)
+                    -> Genome<N> {
+        self.mate_traced(parent_left,
+                         parent_right,
+                         fitness_left,
+                         fitness_right,
+                         prefer_mutate,
+                         &mut Vec::new(),
+                         rng)
+            .0
+    }
+
+    fn mate_traced<R: Rng>(&mut self,
+                           parent_left: &Genome<N>,
+                           parent_right: &Genome<N>,
+                           fitness_left: Fitness,
+                           fitness_right: Fitness,
+                           prefer_mutate: bool,
+                           operators: &mut Vec<Operator>,
+                           rng: &mut R)
+                           -> (Genome<N>, Contributors) {
+        self.mate_with(parent_left,
+                       parent_right,
+                       fitness_left,
+                       fitness_right,
+                       prefer_mutate,
+                       operators,
+                       rng)
+    }
+}
+
+impl<'a, 'c, N, S> StagedMate<Genome<N>> for Mater<'a, N, S, &'c mut GlobalInnovationCache>
+    where N: NodeType + PartialEq + 'a,
+          S: ElementStrategy<N> + Sync + 'a
+{
+    type Stage = Mater<'a, N, S, StagedInnovationCache>;
+
+    fn stage(&self, n: usize) -> Vec<Self::Stage> {
+        let snapshot = Arc::new((*self.global_cache).clone());
+        (0..n)
+            .map(|_| self.with_global_cache(StagedInnovationCache::new(snapshot.clone())))
+            .collect()
+    }
+
+    fn commit<'b, I>(&mut self, stage: Self::Stage, genomes: I)
+        where I: Iterator<Item = &'b mut Genome<N>>,
+              N: 'b
+    {
+        let map = self.global_cache.commit(stage.global_cache);
+        if map.is_empty() {
+            return;
+        }
+        for genome in genomes {
+            if genome.is_renumbered_by(&map) {
+                *genome = genome.renumber(&map);
+            }
+        }
+    }
+}
+
+impl<NT: NodeType + PartialEq> Genome<NT> {
+    /// Determines what changed from `old_genome` to `new_genome`, e.g. from a parent to it's
+    /// offspring.
+    ///
+    /// Nodes and links are identified by their innovation number.
+
+    pub fn diff(old_genome: &Self, new_genome: &Self) -> GenomeDiff {
+        let mut diff = GenomeDiff {
+            added_nodes: Vec::new(),
+            removed_nodes: Vec::new(),
+            added_links: Vec::new(),
+            removed_links: Vec::new(),
+            enabled_links: Vec::new(),
+            disabled_links: Vec::new(),
+            weight_deltas: Vec::new(),
+            changed_node_types: Vec::new(),
+            node_param_deltas: Vec::new(),
+        };
+
+        align_sorted_iterators(old_genome.node_innovation_map.iter(),
+                               new_genome.node_innovation_map.iter(),
+                               |&(kl, _), &(kr, _)| Ord::cmp(kl, kr),
+                               |alignment| {
+            match alignment {
+                Alignment::Match((&ni, &old_node_index), (_, &new_node_index)) => {
+                    if old_genome.network.node(old_node_index).node_type() !=
+                       new_genome.network.node(new_node_index).node_type() {
+                        diff.changed_node_types.push(ni);
+                    }
+
+                    let old_params = old_genome.node_params[&ni];
+                    let new_params = new_genome.node_params[&ni];
+                    if old_params != new_params {
+                        diff.node_param_deltas.push((ni,
+                                                     NodeParams {
+                                                         bias: Weight(new_params.bias.0 -
+                                                                      old_params.bias.0),
+                                                         gain: Weight(new_params.gain.0 -
+                                                                      old_params.gain.0),
+                                                     }));
+                    }
+                }
+                Alignment::Disjoint((&ni, _), LeftOrRight::Left) |
+                Alignment::Excess((&ni, _), LeftOrRight::Left) => diff.removed_nodes.push(ni),
+                Alignment::Disjoint((&ni, _), LeftOrRight::Right) |
+                Alignment::Excess((&ni, _), LeftOrRight::Right) => diff.added_nodes.push(ni),
+            }
+        });
+
+        Self::align_links(old_genome, new_genome, |alignment| {
+            match alignment {
+                Alignment::Match(old_link_ref, new_link_ref) => {
+                    let link_innovation: LinkInnovation = old_link_ref.external_link_id().into();
+                    let old_link = old_link_ref.link();
+                    let new_link = new_link_ref.link();
+
+                    if !old_link.is_active() && new_link.is_active() {
+                        diff.enabled_links.push(link_innovation);
+                    } else if old_link.is_active() && !new_link.is_active() {
+                        diff.disabled_links.push(link_innovation);
+                    }
+
+                    let delta = new_link.weight().0 - old_link.weight().0;
+                    if delta != 0.0 {
+                        diff.weight_deltas.push((link_innovation, delta));
+                    }
+                }
+                Alignment::Disjoint(link_ref, LeftOrRight::Left) |
+                Alignment::Excess(link_ref, LeftOrRight::Left) => {
+                    diff.removed_links.push(link_ref.external_link_id().into());
+                }
+                Alignment::Disjoint(link_ref, LeftOrRight::Right) |
+                Alignment::Excess(link_ref, LeftOrRight::Right) => {
+                    diff.added_links.push(link_ref.external_link_id().into());
+                }
+            }
+        });
+
+        // links are aligned per source node
+        diff.added_links.sort();
+        diff.removed_links.sort();
+        diff.enabled_links.sort();
+        diff.disabled_links.sort();
+        diff.weight_deltas.sort_by(|a, b| a.0.cmp(&b.0));
+
+        diff
+    }
+
+    /// Mutate the genome by changing the type of a random hidden node to `node_type`. Input and
+    /// output nodes are never changed. Only hidden nodes of a different type are considered.
+    ///
+    /// Returns `false` if there is no hidden node of a different type, or if `node_type` does
+    /// not accept both incoming and outgoing links. Otherwise `true`.
+    ///
+    /// # Complexity
+    ///
+    /// The genome is rebuilt with the new node type, which is O(n + m).
+
+    pub fn mutate_change_node_type<R: Rng>(&mut self, node_type: NT, rng: &mut R) -> bool {
+        if !(node_type.accept_incoming_links() && node_type.accept_outgoing_links()) {
+            return false;
+        }
+
+        let candidates: Vec<NodeInnovation> = {
+            let differs = |ni: &NodeInnovation| {
+                let node_index = self.node_innovation_map[ni];
+                *self.network.node(node_index).node_type() != node_type
+            };
+            self.hidden_nodes().into_iter().filter(differs).collect()
+        };
+        let changed_node = match rng.choose(&candidates) {
+            Some(&ni) => ni,
+            None => return false,
+        };
+
+        let genome = self.rebuild(|ni, nt| {
+            if ni == changed_node {
+                Some(node_type.clone())
+            } else {
+                Some(nt.clone())
+            }
+        });
+
+        *self = genome;
+        return true;
+    }
+
+    /// Counts the matching node innovations of `left_genome` and `right_genome` which differ in
+    /// their node type.
+
+    pub fn node_type_mismatches(left_genome: &Self, right_genome: &Self) -> usize {
+        let mut mismatches = 0;
+
+        align_sorted_iterators(left_genome.node_innovation_map.iter(),
+                               right_genome.node_innovation_map.iter(),
+                               |&(kl, _), &(kr, _)| Ord::cmp(kl, kr),
+                               |node_alignment| {
+                                   if let Alignment::Match((_, &left_node_index),
+                                                           (_, &right_node_index)) =
+                                          node_alignment {
+                                       if left_genome.network.node(left_node_index).node_type() !=
+                                          right_genome.network.node(right_node_index).node_type() {
+                                           mismatches += 1;
+                                       }
+                                   }
+                               });
+
+        mismatches
+    }
+}
+
+/// Escapes `s` so that it can be used within a double quoted string of GML or DOT.
+
+fn escape_quoted(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl<NT: NodeType + NodeLabel> Genome<NT> {
+    /// Writes the genome as directed graph in GML format.
+    ///
+    /// Nodes are identified by their innovation number. Each link carries it's innovation
+    /// number, weight and whether it is active. Disabled links are drawn dashed.
+
+    pub fn to_gml<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(writeln!(wr, "graph ["));
+        try!(writeln!(wr, "  directed 1"));
+
+        for node in self.network.nodes() {
+            let ni: NodeInnovation = node.external_node_id().into();
+            try!(writeln!(wr,
+                          "  node [id {} label \"{}\"]",
+                          ni.0,
+                          escape_quoted(&node.node_type().node_label())));
+        }
+
+        let mut result = Ok(());
+        self.network.each_link_ref(|link_ref| {
+            if result.is_err() {
+                return;
+            }
+            let source_node: NodeInnovation = link_ref.external_source_node_id().into();
+            let target_node: NodeInnovation = link_ref.external_target_node_id().into();
+            let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+            let active = link_ref.link().is_active();
+            result = writeln!(wr,
+                              "  edge [source {} target {} innovation {} weight {} active {}{}]",
+                              source_node.0,
+                              target_node.0,
+                              link_innovation.0,
+                              link_ref.link().weight().0,
+                              if active { 1 } else { 0 },
+                              if active { "" } else { " graphics [style \"dashed\"]" });
+        });
+        try!(result);
+
+        writeln!(wr, "]")
+    }
+
+    /// Writes the genome as directed graph in Graphviz DOT format.
+    ///
+    /// Nodes are labeled with their innovation number and node type, links with their
+    /// innovation number and weight. Disabled links are drawn dashed.
+
+    pub fn to_dot<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(writeln!(wr, "digraph genome {{"));
+
+        for node in self.network.nodes() {
+            let ni: NodeInnovation = node.external_node_id().into();
+            try!(writeln!(wr,
+                          "  n{} [label=\"{}: {}\"];",
+                          ni.0,
+                          ni.0,
+                          escape_quoted(&node.node_type().node_label())));
+        }
+
+        let mut result = Ok(());
+        self.network.each_link_ref(|link_ref| {
+            if result.is_err() {
+                return;
+            }
+            let source_node: NodeInnovation = link_ref.external_source_node_id().into();
+            let target_node: NodeInnovation = link_ref.external_target_node_id().into();
+            let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+            result = writeln!(wr,
+                              "  n{} -> n{} [label=\"{}: {}\"{}];",
+                              source_node.0,
+                              target_node.0,
+                              link_innovation.0,
+                              link_ref.link().weight().0,
+                              if link_ref.link().is_active() {
+                                  ""
+                              } else {
+                                  ", style=dashed"
+                              });
+        });
+        try!(result);
+
+        writeln!(wr, "}}")
+    }
+}
+
+/// Written as `(step-sizes sigma ((link sigma)...))`.
+
+impl Persist for StepSizes {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "(step-sizes "));
+        try!(self.sigma.persist(wr));
+        let link_sigmas: Vec<(LinkInnovation, f64)> = self.link_sigmas
+                                                          .iter()
+                                                          .map(|(&link, &sigma)| (link, sigma))
+                                                          .collect();
+        try!(link_sigmas.persist(wr));
+        write!(wr, ") ")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("step-sizes"));
+        let sigma = try!(rd.atom());
+        let link_sigmas: Vec<(LinkInnovation, f64)> = try!(Persist::restore(rd));
+        try!(rd.close());
+        Ok(StepSizes {
+            sigma: sigma,
+            link_sigmas: link_sigmas.into_iter().collect(),
+        })
+    }
+}
+
+/// Written as
+///
+/// ```text
+/// (genome
+///   (nodes (node innovation node-type bias gain)...)
+///   (links (link innovation source target weight active disabled-age)...)
+///   step-sizes)
+/// ```
+///
+/// The `disabled-age` of an active link is 0. Nodes and links are written in the order they are
+/// stored in the network. A restored genome
+/// is built up in the same order, so that it is mutated exactly like the original.
+
+impl<NT: NodeType + Persist> Persist for Genome<NT> {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "(genome\n  (nodes"));
+        for node in self.network.nodes() {
+            let node_innovation: NodeInnovation = node.external_node_id().into();
+            let node_params = self.node_params[&node_innovation];
+            try!(write!(wr, "\n    (node "));
+            try!(node_innovation.persist(wr));
+            try!(node.node_type().persist(wr));
+            try!(node_params.bias.persist(wr));
+            try!(node_params.gain.persist(wr));
+            try!(write!(wr, ")"));
+        }
+
+        let mut links = Vec::new();
+        self.network.each_link_ref(|link_ref| {
+            let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+            let source_node: NodeInnovation = link_ref.external_source_node_id().into();
+            let target_node: NodeInnovation = link_ref.external_target_node_id().into();
+            links.push((link_innovation,
+                        source_node,
+                        target_node,
+                        link_ref.link().weight(),
+                        link_ref.link().is_active()));
+        });
+
+        try!(write!(wr, ")\n  (links"));
+        for &(link_innovation, source_node, target_node, weight, active) in links.iter() {
+            try!(write!(wr, "\n    (link "));
+            try!(link_innovation.persist(wr));
+            try!(source_node.persist(wr));
+            try!(target_node.persist(wr));
+            try!(weight.persist(wr));
+            try!(active.persist(wr));
+            try!(self.disabled_age(link_innovation).unwrap_or(0).persist(wr));
+            try!(write!(wr, ")"));
+        }
+        try!(write!(wr, ")\n  "));
+        try!(self.step_sizes.persist(wr));
+        write!(wr, ")\n")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("genome"));
+        let mut genome = Genome::new();
+
+        try!(rd.open_keyword("nodes"));
+        while !rd.at_close() {
+            try!(rd.open_keyword("node"));
+            let node_innovation = try!(NodeInnovation::restore(rd));
+            let node_type = try!(NT::restore(rd));
+            let node_params = NodeParams {
+                bias: try!(Weight::restore(rd)),
+                gain: try!(Weight::restore(rd)),
+            };
+            try!(rd.close());
+
+            if genome.has_node(node_innovation) {
+                return Err(PersistError::Invalid(format!("duplicate node {:?}", node_innovation)));
+            }
+            genome.add_node_with_params(node_innovation, node_type, node_params);
+        }
+        try!(rd.close());
+
+        try!(rd.open_keyword("links"));
+        while !rd.at_close() {
+            try!(rd.open_keyword("link"));
+            let link_innovation = try!(LinkInnovation::restore(rd));
+            let source_node = try!(NodeInnovation::restore(rd));
+            let target_node = try!(NodeInnovation::restore(rd));
+            let weight = try!(Weight::restore(rd));
+            let active: bool = try!(rd.atom());
+            let disabled_age: usize = try!(rd.atom());
+            try!(rd.close());
+
+            if !(genome.has_node(source_node) && genome.has_node(target_node) &&
+                 genome.valid_link_no_cycle(source_node, target_node)) {
+                return Err(PersistError::Invalid(format!("link {:?} from {:?} to {:?}",
+                                                         link_innovation,
+                                                         source_node,
+                                                         target_node)));
+            }
+            genome.add_link_with_active(source_node, target_node, link_innovation, weight, active);
+            if !active {
+                genome.disabled_ages.insert(link_innovation, disabled_age);
+            }
+        }
+        try!(rd.close());
+
+        genome.step_sizes = try!(Persist::restore(rd));
+        try!(rd.close());
+
+        match genome.validate() {
+            Ok(()) => Ok(genome),
+            Err(violations) => Err(PersistError::Invalid(format!("{:?}", violations))),
+        }
+    }
+}
+
+/// Determines how the input (and bias) nodes of a genome created by `GenomeBuilder` are linked
+/// to the output nodes.
+
+#[derive(Debug, Clone, Copy)]
+pub enum InitialConnectivity {
+    /// No links at all.
+    Unconnected,
+    /// Each input (and bias) node is linked to each output node.
+    Full,
+    /// Each of the links of `Full` is created with the given probability.
+    Sparse(Prob),
+}
+
+/// Creates minimal starting genomes consisting only of input, output and an optional bias
+/// node.
+///
+/// The node innovations are allocated once through the `GlobalCache` when the builder is
+/// created, so that all genomes built by the same builder share them. Link innovations are
+/// looked up in the `GlobalCache` as well.
+
+#[derive(Debug, Clone)]
+pub struct GenomeBuilder<NT: NodeType> {
+    input_nodes: Vec<(NodeInnovation, NT)>,
+    output_nodes: Vec<(NodeInnovation, NT)>,
+    bias_node: Option<(NodeInnovation, NT)>,
+}
+
+impl<NT: NodeType> GenomeBuilder<NT> {
+    /// Allocates node innovations for the `input_types`, followed by the `output_types` and the
+    /// `bias_type`.
+
+    pub fn new<G: GlobalCache>(input_types: Vec<NT>,
+                               output_types: Vec<NT>,
+                               bias_type: Option<NT>,
+                               cache: &mut G)
+                               -> Self {
+        let input_nodes = input_types.into_iter()
+                                     .map(|nt| (cache.create_node_innovation(), nt))
+                                     .collect();
+        let output_nodes = output_types.into_iter()
+                                       .map(|nt| (cache.create_node_innovation(), nt))
+                                       .collect();
+        let bias_node = bias_type.map(|nt| (cache.create_node_innovation(), nt));
+
+        GenomeBuilder {
+            input_nodes: input_nodes,
+            output_nodes: output_nodes,
+            bias_node: bias_node,
+        }
+    }
+
+    /// Builds a new genome. The links are created according to `connectivity`, each with a
+    /// random weight from `weight_range`. Links which are not allowed by the node types are
+    /// skipped.
+
+    pub fn build<R, G>(&self,
+                       connectivity: InitialConnectivity,
+                       weight_range: &WeightRange,
+                       cache: &mut G,
+                       rng: &mut R)
+                       -> Genome<NT>
+        where R: Rng,
+              G: GlobalCache
+    {
+        let mut genome = Genome::new();
+
+        for &(ni, ref nt) in self.input_nodes
+                                 .iter()
+                                 .chain(self.output_nodes.iter())
+                                 .chain(self.bias_node.iter()) {
+            genome.add_node(ni, nt.clone());
+        }
+
+        let source_nodes: Vec<NodeInnovation> = self.input_nodes
+                                                    .iter()
+                                                    .chain(self.bias_node.iter())
+                                                    .map(|&(ni, _)| ni)
+                                                    .collect();
+
+        for &source_node in source_nodes.iter() {
+            for &(target_node, _) in self.output_nodes.iter() {
+                let create = match connectivity {
+                    InitialConnectivity::Unconnected => false,
+                    InitialConnectivity::Full => true,
+                    InitialConnectivity::Sparse(p) => p.flip(rng),
+                };
+
+                if create && genome.valid_link(source_node, target_node) {
+                    let link_innovation = cache.get_or_create_link_innovation(source_node,
+                                                                              target_node);
+                    genome.add_link(source_node,
+                                    target_node,
+                                    link_innovation,
+                                    weight_range.random_weight(rng));
+                }
+            }
+        }
+
+        genome
+    }
+}
+
+/// The phenotype of an acyclic genome.
+///
+/// All nodes are stored in topological order, so that a single pass over the nodes is
+/// sufficient to activate the network. Only active links are taken into account.
+
+#[derive(Clone, Debug)]
+pub struct FeedForwardNetwork<NT: ActivationNodeType> {
+    // node types in topological order
+    node_types: Vec<NT>,
+    // node parameters in topological order
+    node_params: Vec<NodeParams>,
+    // for each node, the (source node, weight) of each incoming active link.
+    incoming_links: Vec<Vec<(usize, f64)>>,
+    // positions of the input nodes, ordered by node innovation.
+    input_nodes: Vec<usize>,
+    // positions of the output nodes, ordered by node innovation.
+    output_nodes: Vec<usize>,
+}
+
+impl<NT: ActivationNodeType> FeedForwardNetwork<NT> {
+    /// Compiles `genome` into a topologically ordered network. Inputs and outputs are ordered
+    /// by node innovation.
+
+    pub fn from_genome(genome: &Genome<NT>) -> Self {
+        let mut nodes: BTreeMap<NodeInnovation, NT> = BTreeMap::new();
+        genome.visit_nodes(|node_innovation, node_type| {
+            nodes.insert(node_innovation, node_type);
+        });
+
+        let mut in_degree: BTreeMap<NodeInnovation, usize> = nodes.keys()
+                                                                  .map(|&ni| (ni, 0))
+                                                                  .collect();
+        let mut outgoing: BTreeMap<NodeInnovation, Vec<NodeInnovation>> = BTreeMap::new();
+        genome.visit_active_links(|source_node, target_node, _| {
+            *in_degree.get_mut(&target_node).unwrap() += 1;
+            outgoing.entry(source_node).or_insert_with(Vec::new).push(target_node);
+        });
+
+        // Kahn's algorithm.
+        let mut queue: VecDeque<NodeInnovation> = in_degree.iter()
+                                                           .filter(|&(_, &d)| d == 0)
+                                                           .map(|(&ni, _)| ni)
+                                                           .collect();
+        let mut position: BTreeMap<NodeInnovation, usize> = BTreeMap::new();
+        let mut node_types = Vec::with_capacity(nodes.len());
+        let mut node_params = Vec::with_capacity(nodes.len());
+
+        while let Some(ni) = queue.pop_front() {
+            position.insert(ni, node_types.len());
+            node_types.push(nodes[&ni].clone());
+            node_params.push(genome.node_params[&ni]);
+
+            if let Some(targets) = outgoing.get(&ni) {
+                for target in targets {
+                    let d = in_degree.get_mut(target).unwrap();
+                    *d -= 1;
+                    if *d == 0 {
+                        queue.push_back(*target);
+                    }
+                }
+            }
+        }
+
+        assert!(node_types.len() == nodes.len(), "Genome contains a cycle");
+
+        let mut incoming_links: Vec<Vec<(usize, f64)>> = node_types.iter()
+                                                                   .map(|_| Vec::new())
+                                                                   .collect();
+        genome.visit_active_links(|source_node, target_node, weight| {
+            incoming_links[position[&target_node]].push((position[&source_node], weight.0));
+        });
+
+        let mut input_nodes = Vec::new();
+        let mut output_nodes = Vec::new();
+        for (ni, node_type) in nodes.iter() {
+            match node_type.node_role() {
+                NodeRole::Input => input_nodes.push(position[ni]),
+                NodeRole::Output => output_nodes.push(position[ni]),
+                NodeRole::Bias | NodeRole::Hidden => {}
+            }
+        }
+
+        FeedForwardNetwork {
+            node_types: node_types,
+            node_params: node_params,
+            incoming_links: incoming_links,
+            input_nodes: input_nodes,
+            output_nodes: output_nodes,
+        }
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.input_nodes.len()
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.output_nodes.len()
+    }
+
+    /// Feeds `inputs` through the network and returns the activation of the output nodes.
+    ///
+    /// # Panics
+    ///
+    /// If the length of `inputs` does not match the number of input nodes.
+
+    pub fn activate(&self, inputs: &[f64]) -> Vec<f64> {
+        assert!(inputs.len() == self.input_nodes.len());
+
+        let mut values = vec![0.0; self.node_types.len()];
+        for (&node, &input) in self.input_nodes.iter().zip(inputs.iter()) {
+            values[node] = input;
+        }
 
-                modifications > 0
-            }
-            MutateMethod::AddConnection => {
-                let link_weight = self.element_strategy
-                                      .link_weight_range()
-                                      .random_weight(rng);
-                offspring.mutate_add_link(link_weight, self.global_cache, rng)
-            }
-            MutateMethod::EnableConnection => offspring.mutate_enable_link(rng),
-            MutateMethod::DeleteConnection => offspring.mutate_delete_link(rng),
-            MutateMethod::AddNode => {
-                let second_link_weight = self.element_strategy.full_link_weight();
-                let node_type = self.element_strategy.random_node_type(rng);
-                offspring.mutate_add_node(node_type, second_link_weight, self.global_cache, rng)
-            }
+        for (i, node_type) in self.node_types.iter().enumerate() {
+            values[i] = match node_type.node_role() {
+                NodeRole::Input => values[i],
+                NodeRole::Bias => 1.0,
+                NodeRole::Hidden | NodeRole::Output => {
+                    let sum = self.incoming_links[i]
+                                  .iter()
+                                  .fold(0.0, |sum, &(source, weight)| sum + weight * values[source]);
+                    let params = &self.node_params[i];
+                    node_type.activate(params.gain.0 * sum + params.bias.0)
+                }
+            };
         }
+
+        self.output_nodes.iter().map(|&node| values[node]).collect()
     }
 }
 
+/// A single step of a `CompiledNetwork`. There is exactly one instruction per node.
+
+#[derive(Clone, Copy, Debug)]
+enum Instruction {
+    /// Load the input value with the given index.
+    Input(usize),
+    /// Load 1.0.
+    Bias,
+    /// Sum up the links `first_link..end_link`, apply gain and bias, followed by the activation
+    /// function of the node.
+    Activate {
+        first_link: usize,
+        end_link: usize,
+    },
+}
 
-impl<'a, N, S, C> Mate<Genome<N>> for Mater<'a, N, S, C>
-    where N: NodeType + 'a,
-          S: ElementStrategy<N> + 'a,
-          C: GlobalCache + 'a
-{
-    // Add an argument that descibes whether both genomes are of equal fitness.
-    // Pass individual, which includes the fitness.
-    fn mate<R: Rng>(&mut self,
-                    parent_left: &Genome<N>,
-                    parent_right: &Genome<N>,
-                    prefer_mutate: bool,
-                    rng: &mut R)
-                    -> Genome<N> {
-        if prefer_mutate == false && self.p_crossover.flip(rng) {
-            Genome::crossover(parent_left, parent_right, &self.p_crossover_detail, rng)
-        } else {
-            // mutate
-            let mutate_method = MutateMethod::random_with(&self.mutate_weights, rng);
+/// A flat and cache friendly form of a `FeedForwardNetwork`, intended for evaluating the same
+/// network many times.
+///
+/// The network is a contiguous list of instructions in topological order, operating on a
+/// dense buffer of node values. The links are stored as struct-of-arrays, grouped by their
+/// target node.
+
+#[derive(Clone, Debug)]
+pub struct CompiledNetwork<NT: ActivationNodeType> {
+    instructions: Vec<Instruction>,
+    node_types: Vec<NT>,
+    node_biases: Vec<f64>,
+    node_gains: Vec<f64>,
+    link_sources: Vec<usize>,
+    link_weights: Vec<f64>,
+    num_inputs: usize,
+    output_nodes: Vec<usize>,
+}
 
-            {
-                let mut offspring = parent_left.clone();
+impl<NT: ActivationNodeType> CompiledNetwork<NT> {
+    pub fn from_genome(genome: &Genome<NT>) -> Self {
+        CompiledNetwork::from_feed_forward(&FeedForwardNetwork::from_genome(genome))
+    }
 
-                let modified = self.mutate(&mut offspring, mutate_method, rng);
+    pub fn from_feed_forward(network: &FeedForwardNetwork<NT>) -> Self {
+        let mut input_index = vec![None; network.node_types.len()];
+        for (k, &node) in network.input_nodes.iter().enumerate() {
+            input_index[node] = Some(k);
+        }
 
-                if modified {
-                    return offspring;
+        let mut instructions = Vec::with_capacity(network.node_types.len());
+        let mut link_sources = Vec::new();
+        let mut link_weights = Vec::new();
+
+        for (i, node_type) in network.node_types.iter().enumerate() {
+            let instruction = match node_type.node_role() {
+                NodeRole::Input => Instruction::Input(input_index[i].unwrap()),
+                NodeRole::Bias => Instruction::Bias,
+                NodeRole::Hidden | NodeRole::Output => {
+                    let first_link = link_sources.len();
+                    for &(source, weight) in network.incoming_links[i].iter() {
+                        link_sources.push(source);
+                        link_weights.push(weight);
+                    }
+                    Instruction::Activate {
+                        first_link: first_link,
+                        end_link: link_sources.len(),
+                    }
                 }
-            }
+            };
+            instructions.push(instruction);
+        }
 
-            info!("no change in mutate left genome. mutate_method: {:?}", mutate_method);
+        CompiledNetwork {
+            instructions: instructions,
+            node_types: network.node_types.clone(),
+            node_biases: network.node_params.iter().map(|p| p.bias.0).collect(),
+            node_gains: network.node_params.iter().map(|p| p.gain.0).collect(),
+            link_sources: link_sources,
+            link_weights: link_weights,
+            num_inputs: network.input_nodes.len(),
+            output_nodes: network.output_nodes.clone(),
+        }
+    }
 
-            {
-                let mut offspring = parent_right.clone();
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
 
-                let modified = self.mutate(&mut offspring, mutate_method, rng);
+    pub fn num_outputs(&self) -> usize {
+        self.output_nodes.len()
+    }
 
-                if modified {
-                    return offspring;
+    /// Runs the instructions for `inputs`, using `values` as node buffer. The output values are
+    /// appended to `outputs`.
+
+    fn activate_into(&self, inputs: &[f64], values: &mut [f64], outputs: &mut Vec<f64>) {
+        assert!(inputs.len() == self.num_inputs);
+        debug_assert!(values.len() == self.instructions.len());
+
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            values[i] = match *instruction {
+                Instruction::Input(k) => inputs[k],
+                Instruction::Bias => 1.0,
+                Instruction::Activate { first_link, end_link } => {
+                    let mut sum = 0.0;
+                    for l in first_link..end_link {
+                        sum += self.link_weights[l] * values[self.link_sources[l]];
+                    }
+                    self.node_types[i].activate(self.node_gains[i] * sum + self.node_biases[i])
                 }
+            };
+        }
 
-                info!("no change in mutate right genome");
+        outputs.extend(self.output_nodes.iter().map(|&node| values[node]));
+    }
 
-                return offspring;
-            }
+    /// Feeds `inputs` through the network and returns the activation of the output nodes.
+
+    pub fn activate(&self, inputs: &[f64]) -> Vec<f64> {
+        let mut values = vec![0.0; self.instructions.len()];
+        let mut outputs = Vec::with_capacity(self.output_nodes.len());
+        self.activate_into(inputs, &mut values, &mut outputs);
+        outputs
+    }
+
+    /// Evaluates the network for each input vector of `inputs`. This reuses the node buffer for
+    /// all input vectors.
+    ///
+    /// Returns the outputs of all evaluations in a single vector. The outputs of the `i`-th input
+    /// vector are located at `i * num_outputs()..(i + 1) * num_outputs()`.
+
+    pub fn activate_batch(&self, inputs: &[Vec<f64>]) -> Vec<f64> {
+        let mut values = vec![0.0; self.instructions.len()];
+        let mut outputs = Vec::with_capacity(inputs.len() * self.output_nodes.len());
+        for input in inputs.iter() {
+            self.activate_into(input, &mut values, &mut outputs);
         }
+        outputs
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{NodeType, Genome, NodeInnovation, LinkInnovation};
+    use super::{NodeType, Genome, AnyInnovation, NodeInnovation, LinkInnovation,
+                FeedForwardNetwork, CompiledNetwork, NodeParams, GenomeDistance, GlobalCache,
+                GlobalInnovationCache, GenomeBuilder, InitialConnectivity, PruneOptions,
+                GenomeViolation, GenomeDiff, SelfAdaptation, StepSizes,
+                ConcurrentInnovationCache, Mater, ElementStrategy};
+    use traits::{Distance, Mate};
+    use mutate::{MutateMethod, MutateMethodWeighting, MutationSchedule, MutationCount};
+    use lineage::{Operator, Contributors};
+    use fitness::Fitness;
+    use std::marker::PhantomData;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use rand::distributions::{Normal, IndependentSample};
+    use crossover::{ProbabilisticCrossover, BlendMethod};
+    use prob::Prob;
+    use weight::{WeightRange, WeightPerturbanceMethod};
+    use rand::{self, Rng, XorShiftRng, SeedableRng};
+    use genomes::{ActivationNodeType, NodeRole, NodeLabel};
     use weight::Weight;
     use innovation::InnovationRange;
+    use std::sync::Arc;
+    use std::thread;
+    use std::io::{self, Write};
+    use persist::{Persist, Reader, PersistError, save, load};
 
     #[derive(Clone, Debug)]
     struct NT;
@@ -1295,4 +3605,1189 @@ mod tests {
                    Genome::combined_alignment_metric(&left, &right).node_metric);
     }
 
+    #[derive(Clone, Debug, PartialEq)]
+    enum Neuron {
+        Input,
+        Hidden,
+        Tanh,
+        Output,
+    }
+
+    impl NodeType for Neuron {
+        fn accept_incoming_links(&self) -> bool {
+            *self != Neuron::Input
+        }
+        fn accept_outgoing_links(&self) -> bool {
+            *self != Neuron::Output
+        }
+    }
+
+    impl NodeLabel for Neuron {
+        fn node_label(&self) -> String {
+            format!("{:?}", self)
+        }
+    }
+
+    impl ActivationNodeType for Neuron {
+        fn node_role(&self) -> NodeRole {
+            match *self {
+                Neuron::Input => NodeRole::Input,
+                Neuron::Hidden | Neuron::Tanh => NodeRole::Hidden,
+                Neuron::Output => NodeRole::Output,
+            }
+        }
+        fn activate(&self, input: f64) -> f64 {
+            match *self {
+                Neuron::Tanh => input.tanh(),
+                _ => input,
+            }
+        }
+    }
+
+    impl Persist for Neuron {
+        fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+            write!(wr, "{:?} ", self)
+        }
+
+        fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+            match try!(rd.next()) {
+                "Input" => Ok(Neuron::Input),
+                "Hidden" => Ok(Neuron::Hidden),
+                "Tanh" => Ok(Neuron::Tanh),
+                "Output" => Ok(Neuron::Output),
+                token => Err(PersistError::Invalid(token.to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_feed_forward_activate() {
+        let mut genome = Genome::<Neuron>::new();
+        let i0 = NodeInnovation(0);
+        let i1 = NodeInnovation(1);
+        let o = NodeInnovation(2);
+        let h = NodeInnovation(3);
+
+        genome.add_node(i0, Neuron::Input);
+        genome.add_node(i1, Neuron::Input);
+        genome.add_node(o, Neuron::Output);
+        genome.add_node(h, Neuron::Hidden);
+
+        genome.add_link(i0, h, LinkInnovation(0), Weight(1.0));
+        genome.add_link(i1, h, LinkInnovation(1), Weight(2.0));
+        genome.add_link(h, o, LinkInnovation(2), Weight(0.5));
+        genome.add_link(i0, o, LinkInnovation(3), Weight(1.0));
+        genome.add_link_with_active(i1, o, LinkInnovation(4), Weight(100.0), false);
+
+        let net = FeedForwardNetwork::from_genome(&genome);
+        assert_eq!(2, net.num_inputs());
+        assert_eq!(1, net.num_outputs());
+        assert_eq!(vec![3.5], net.activate(&[1.0, 2.0]));
+        assert_eq!(vec![0.0], net.activate(&[0.0, 0.0]));
+    }
+
+    #[test]
+    fn test_compiled_network_activate_batch() {
+        let mut genome = Genome::<Neuron>::new();
+        let i0 = NodeInnovation(0);
+        let i1 = NodeInnovation(1);
+        let o0 = NodeInnovation(2);
+        let o1 = NodeInnovation(3);
+        let h = NodeInnovation(4);
+
+        genome.add_node(i0, Neuron::Input);
+        genome.add_node(i1, Neuron::Input);
+        genome.add_node(o0, Neuron::Output);
+        genome.add_node(o1, Neuron::Output);
+        genome.add_node(h, Neuron::Hidden);
+
+        genome.add_link(i0, h, LinkInnovation(0), Weight(1.0));
+        genome.add_link(i1, h, LinkInnovation(1), Weight(-1.0));
+        genome.add_link(h, o0, LinkInnovation(2), Weight(2.0));
+        genome.add_link(i1, o1, LinkInnovation(3), Weight(0.5));
+
+        let net = FeedForwardNetwork::from_genome(&genome);
+        let compiled = CompiledNetwork::from_genome(&genome);
+        assert_eq!(2, compiled.num_inputs());
+        assert_eq!(2, compiled.num_outputs());
+
+        let inputs = vec![vec![1.0, 2.0], vec![0.0, 0.0], vec![3.0, -1.0]];
+        let outputs = compiled.activate_batch(&inputs);
+        assert_eq!(vec![-2.0, 1.0, 0.0, 0.0, 8.0, -0.5], outputs);
+
+        for (input, output) in inputs.iter().zip(outputs.chunks(2)) {
+            assert_eq!(net.activate(input), output.to_vec());
+            assert_eq!(compiled.activate(input), output.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_node_params() {
+        let mut genome = Genome::<Neuron>::new();
+        let i0 = NodeInnovation(0);
+        let o = NodeInnovation(1);
+
+        genome.add_node(i0, Neuron::Input);
+        genome.add_node_with_params(o,
+                                    Neuron::Output,
+                                    NodeParams {
+                                        bias: Weight(1.0),
+                                        gain: Weight(2.0),
+                                    });
+        genome.add_link(i0, o, LinkInnovation(0), Weight(0.5));
+
+        assert_eq!(vec![4.0], FeedForwardNetwork::from_genome(&genome).activate(&[3.0]));
+        assert_eq!(vec![4.0], CompiledNetwork::from_genome(&genome).activate(&[3.0]));
+
+        let mut mutated = genome.clone();
+        let mut rng = rand::thread_rng();
+        let modifications =
+            mutated.mutate_node_params_uniformly(Prob::new(0.0),
+                                                 &WeightPerturbanceMethod::Random,
+                                                 &WeightRange::bipolar(0.5),
+                                                 &WeightRange::bipolar(0.5),
+                                                 &mut rng);
+        // only the output node is considered.
+        assert_eq!(1, modifications);
+        assert_eq!(0.0, mutated.node_params(i0).unwrap().distance(&NodeParams::new()));
+
+        let params_distance = genome.node_params(o).unwrap().distance(&mutated.node_params(o).unwrap());
+        let d = GenomeDistance {
+            excess: 1.0,
+            disjoint: 1.0,
+            weight: 1.0,
+            node_params: 1.0,
+            node_excess: 1.0,
+            node_disjoint: 1.0,
+            node_type: 1.0,
+        };
+        assert_eq!(params_distance / 2.0, d.distance(&genome, &mutated));
+    }
+
+    #[test]
+    fn test_node_distance() {
+        let mut left = Genome::<Neuron>::new();
+        let mut right = Genome::<Neuron>::new();
+
+        // both genomes have identical links, but differ in their hidden nodes.
+        for genome in vec![&mut left, &mut right] {
+            genome.add_node(NodeInnovation(0), Neuron::Input);
+            genome.add_node(NodeInnovation(1), Neuron::Output);
+            genome.add_link(NodeInnovation(0), NodeInnovation(1), LinkInnovation(0), Weight(1.0));
+        }
+        left.add_node(NodeInnovation(2), Neuron::Hidden);
+        right.add_node(NodeInnovation(2), Neuron::Output);
+        right.add_node(NodeInnovation(3), Neuron::Hidden);
+
+        assert_eq!(1, Genome::node_type_mismatches(&left, &right));
+
+        let links_only = GenomeDistance {
+            excess: 1.0,
+            disjoint: 1.0,
+            weight: 1.0,
+            node_params: 0.0,
+            node_excess: 0.0,
+            node_disjoint: 0.0,
+            node_type: 0.0,
+        };
+        assert_eq!(0.0, links_only.distance(&left, &right));
+
+        let nodes = GenomeDistance {
+            excess: 0.0,
+            disjoint: 0.0,
+            weight: 0.0,
+            node_params: 0.0,
+            node_excess: 4.0,
+            node_disjoint: 0.0,
+            node_type: 3.0,
+        };
+        // 1 of 4 nodes is excess, 1 of 3 matching nodes differs in type.
+        assert_eq!(1.0 + 1.0, nodes.distance(&left, &right));
+    }
+
+    fn input_hidden_output(cache: &mut GlobalInnovationCache) -> Genome<Neuron> {
+        let mut genome = Genome::<Neuron>::new();
+        let i0 = cache.create_node_innovation();
+        let h = cache.create_node_innovation();
+        let o = cache.create_node_innovation();
+        genome.add_node(i0, Neuron::Input);
+        genome.add_node(h, Neuron::Hidden);
+        genome.add_node(o, Neuron::Output);
+        genome.add_link(i0, h, cache.get_or_create_link_innovation(i0, h), Weight(0.5));
+        genome.add_link(h, o, cache.get_or_create_link_innovation(h, o), Weight(0.5));
+        genome
+    }
+
+    #[test]
+    fn test_mutate_delete_node() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let range = WeightRange::bipolar(1.0);
+
+        let mut genome = input_hidden_output(&mut cache);
+        assert!(genome.mutate_delete_node(false, &range, &mut cache, &mut rng));
+        assert_eq!(2, genome.node_count());
+        assert_eq!(0, genome.link_count());
+
+        // no hidden node left
+        assert!(!genome.mutate_delete_node(false, &range, &mut cache, &mut rng));
+
+        let mut genome = input_hidden_output(&mut cache);
+        assert!(genome.mutate_delete_node(true, &range, &mut cache, &mut rng));
+        assert_eq!(2, genome.node_count());
+        assert_eq!(1, genome.link_count());
+        assert_eq!(vec![0.25], FeedForwardNetwork::from_genome(&genome).activate(&[1.0]));
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut cache = GlobalInnovationCache::new();
+        let mut genome = input_hidden_output(&mut cache);
+        let i0 = NodeInnovation(0);
+        let o = NodeInnovation(2);
+
+        // no path to the output
+        let h1 = cache.create_node_innovation();
+        genome.add_node(h1, Neuron::Hidden);
+        genome.add_link(i0, h1, cache.get_or_create_link_innovation(i0, h1), Weight(0.5));
+
+        // unreachable from the input
+        let h2 = cache.create_node_innovation();
+        genome.add_node(h2, Neuron::Hidden);
+        genome.add_link(h2, o, cache.get_or_create_link_innovation(h2, o), Weight(0.5));
+
+        let disabled_link = cache.get_or_create_link_innovation(i0, o);
+        genome.add_link_with_active(i0, o, disabled_link, Weight(0.5), false);
+
+        assert_eq!(vec![h1, h2], genome.dead_nodes());
+        assert_eq!(vec![disabled_link], genome.disabled_links());
+
+        let mut only_nodes = genome.clone();
+        let report = only_nodes.prune(&PruneOptions {
+            dead_nodes: true,
+            disabled_links: false,
+            max_disabled_age: 0,
+        });
+        assert_eq!(vec![h1, h2], report.removed_nodes);
+        assert_eq!(2, report.removed_links.len());
+        assert_eq!(3, only_nodes.node_count());
+        assert_eq!(3, only_nodes.link_count());
+
+        let options = PruneOptions {
+            dead_nodes: true,
+            disabled_links: true,
+            max_disabled_age: 1,
+        };
+
+        // the link has not been disabled long enough.
+        let mut young = genome.clone();
+        assert_eq!(2, young.prune(&options).removed_links.len());
+        assert_eq!(Some(0), young.disabled_age(disabled_link));
+        young.age_disabled_links();
+        assert!(young.prune(&options).is_empty());
+
+        genome.age_disabled_links();
+        genome.age_disabled_links();
+        assert_eq!(Some(2), genome.disabled_age(disabled_link));
+        let report = genome.prune(&options);
+        assert_eq!(3, report.removed_links.len());
+        assert_eq!(3, genome.node_count());
+        assert_eq!(2, genome.link_count());
+        assert_eq!(vec![0.25], FeedForwardNetwork::from_genome(&genome).activate(&[1.0]));
+
+        // nothing left to prune
+        assert!(genome.prune(&options).is_empty());
+    }
+
+    #[test]
+    fn test_disabled_age() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let mut genome = Genome::<Neuron>::new();
+        let i = cache.create_node_innovation();
+        let o = cache.create_node_innovation();
+        genome.add_node(i, Neuron::Input);
+        genome.add_node(o, Neuron::Output);
+        let link = cache.get_or_create_link_innovation(i, o);
+        genome.add_link(i, o, link, Weight(0.5));
+        assert_eq!(None, genome.disabled_age(link));
+
+        // splitting the link disables it.
+        assert!(genome.mutate_add_node(Neuron::Hidden, Weight(1.0), &mut cache, &mut rng));
+        assert_eq!(Some(0), genome.disabled_age(link));
+        genome.age_disabled_links();
+        genome.age_disabled_links();
+        assert_eq!(Some(2), genome.disabled_age(link));
+        assert!(genome.validate().is_ok());
+
+        // the age survives crossover with a genome having the link active, a rebuild and
+        // persisting.
+        let mut active = Genome::<Neuron>::new();
+        active.add_node(i, Neuron::Input);
+        active.add_node(o, Neuron::Output);
+        active.add_link(i, o, link, Weight(0.5));
+        let range = WeightRange::bipolar(1.0);
+        let c = ProbabilisticCrossover {
+            prob_match_left: Prob::new(1.0),
+            prob_disjoint_left: Prob::new(1.0),
+            prob_excess_left: Prob::new(1.0),
+            prob_disjoint_right: Prob::new(0.0),
+            prob_excess_right: Prob::new(0.0),
+            blend: BlendMethod::Select,
+        };
+        let offspring = Genome::crossover(&genome, &active, &c, &range, &range, &range, &mut rng);
+        assert_eq!(Some(2), offspring.disabled_age(link));
+        let offspring = Genome::crossover(&active, &genome, &c, &range, &range, &range, &mut rng);
+        assert_eq!(None, offspring.disabled_age(link));
+
+        let changed = genome.rebuild(|_, nt| Some(nt.clone()));
+        assert_eq!(Some(2), changed.disabled_age(link));
+
+        let mut buf = Vec::new();
+        save(&genome, &mut buf).unwrap();
+        let restored: Genome<Neuron> = load(&String::from_utf8(buf).unwrap()).unwrap();
+        assert_eq!(Some(2), restored.disabled_age(link));
+
+        // enabling the link again drops the age.
+        assert!(genome.mutate_enable_link(&mut rng));
+        assert_eq!(None, genome.disabled_age(link));
+        assert!(genome.validate().is_ok());
+    }
+
+    #[test]
+    fn test_export() {
+        let mut cache = GlobalInnovationCache::new();
+        let mut genome = input_hidden_output(&mut cache);
+        let i0 = NodeInnovation(0);
+        let o = NodeInnovation(2);
+        let disabled_link = cache.get_or_create_link_innovation(i0, o);
+        genome.add_link_with_active(i0, o, disabled_link, Weight(-1.0), false);
+
+        let mut dot = Vec::new();
+        genome.to_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph genome {\n"));
+        assert!(dot.contains("  n1 [label=\"1: Hidden\"];\n"));
+        assert!(dot.contains("  n0 -> n1 [label=\"0: 0.5\"];\n"));
+        assert!(dot.contains("  n0 -> n2 [label=\"2: -1\", style=dashed];\n"));
+        assert!(dot.ends_with("}\n"));
+
+        let mut gml = Vec::new();
+        genome.to_gml(&mut gml).unwrap();
+        let gml = String::from_utf8(gml).unwrap();
+        assert!(gml.contains("  node [id 2 label \"Output\"]\n"));
+        assert!(gml.contains("  edge [source 1 target 2 innovation 1 weight 0.5 active 1]\n"));
+        assert!(gml.contains("  edge [source 0 target 2 innovation 2 weight -1 active 0 \
+                              graphics [style \"dashed\"]]\n"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut cache = GlobalInnovationCache::new();
+        let genome = input_hidden_output(&mut cache);
+        assert_eq!(Ok(()), genome.validate());
+
+        let i0 = NodeInnovation(0);
+        let h = NodeInnovation(1);
+        let o = NodeInnovation(2);
+
+        let mut missing_params = genome.clone();
+        missing_params.node_params.remove(&h);
+        missing_params.node_params.insert(NodeInnovation(99), NodeParams::new());
+        assert_eq!(Err(vec![GenomeViolation::MissingNodeParams(h),
+                            GenomeViolation::DanglingNodeParams(NodeInnovation(99))]),
+                   missing_params.validate());
+
+        let mut dangling = genome.clone();
+        let node_index = dangling.node_innovation_map[&h];
+        dangling.node_innovation_map.insert(NodeInnovation(99), node_index);
+        assert_eq!(Err(vec![GenomeViolation::DanglingMapEntry(NodeInnovation(99))]),
+                   dangling.validate());
+
+        let mut aged = genome.clone();
+        aged.disabled_ages.insert(LinkInnovation(0), 3);
+        assert_eq!(Err(vec![GenomeViolation::DisabledAgeMismatch(LinkInnovation(0))]),
+                   aged.validate());
+
+        // bypass the checks of `add_link`
+        let mut duplicate = genome.clone();
+        let i0_index = duplicate.node_innovation_map[&i0];
+        let o_index = duplicate.node_innovation_map[&o];
+        let _ = duplicate.network.add_link_with_active(i0_index,
+                                                       o_index,
+                                                       Weight(0.5),
+                                                       AnyInnovation(0),
+                                                       true);
+        assert_eq!(Err(vec![GenomeViolation::DuplicateLinkInnovation(LinkInnovation(0))]),
+                   duplicate.validate());
+
+        // a cycle of 1, 2 and 3 with 4 behind it, which 5 leads into.
+        let n = |i| NodeInnovation(i);
+        let links = [(n(1), n(2)), (n(2), n(3)), (n(3), n(1)), (n(3), n(4)), (n(5), n(1))];
+        assert_eq!(vec![n(1), n(2), n(3), n(4)], Genome::<Neuron>::cyclic_nodes(&links));
+        assert!(Genome::<Neuron>::cyclic_nodes(&links[1..]).is_empty());
+    }
+
+    /// A node type which accepts links as long as it's shared switch is on. Turning the switch
+    /// off invalidates the links of existing genomes, which the genome itself never allows.
+    #[derive(Clone, Debug)]
+    struct Switched(Arc<AtomicBool>);
+
+    impl Switched {
+        fn is_on(&self) -> bool {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    impl PartialEq for Switched {
+        fn eq(&self, other: &Self) -> bool {
+            self.is_on() == other.is_on()
+        }
+    }
+
+    impl NodeType for Switched {
+        fn accept_incoming_links(&self) -> bool {
+            self.is_on()
+        }
+        fn accept_outgoing_links(&self) -> bool {
+            self.is_on()
+        }
+    }
+
+    struct SwitchedStrategy(Arc<AtomicBool>);
+
+    impl ElementStrategy<Switched> for SwitchedStrategy {
+        fn link_weight_range(&self) -> WeightRange {
+            WeightRange::bipolar(1.0)
+        }
+        fn full_link_weight(&self) -> Weight {
+            Weight(1.0)
+        }
+        fn random_node_type<R: Rng>(&self, _rng: &mut R) -> Switched {
+            Switched(self.0.clone())
+        }
+    }
+
+    fn switched_genome(source: &Arc<AtomicBool>, target: &Arc<AtomicBool>) -> Genome<Switched> {
+        let mut genome = Genome::new();
+        genome.add_node(NodeInnovation(0), Switched(source.clone()));
+        genome.add_node(NodeInnovation(1), Switched(target.clone()));
+        genome.add_link(NodeInnovation(0), NodeInnovation(1), LinkInnovation(0), Weight(0.5));
+        genome
+    }
+
+    #[test]
+    fn test_validate_non_accepting_nodes() {
+        let source = Arc::new(AtomicBool::new(true));
+        let target = Arc::new(AtomicBool::new(true));
+        let genome = switched_genome(&source, &target);
+        assert_eq!(Ok(()), genome.validate());
+
+        source.store(false, Ordering::SeqCst);
+        assert_eq!(Err(vec![GenomeViolation::LinkFromNonAcceptingNode(LinkInnovation(0),
+                                                                      NodeInnovation(0))]),
+                   genome.validate());
+
+        source.store(true, Ordering::SeqCst);
+        target.store(false, Ordering::SeqCst);
+        assert_eq!(Err(vec![GenomeViolation::LinkIntoNonAcceptingNode(LinkInnovation(0),
+                                                                      NodeInnovation(1))]),
+                   genome.validate());
+    }
+
+    /// A `Mater` which only mutates link weights.
+
+    fn switched_mater<'a>(cache: &'a mut GlobalInnovationCache,
+                          strategy: &'a SwitchedStrategy)
+                          -> Mater<'a, Switched, SwitchedStrategy, &'a mut GlobalInnovationCache> {
+        Mater {
+            p_crossover: Prob::new(0.0),
+            p_crossover_detail: ProbabilisticCrossover {
+                prob_match_left: Prob::new(0.5),
+                prob_disjoint_left: Prob::new(0.5),
+                prob_excess_left: Prob::new(0.5),
+                prob_disjoint_right: Prob::new(0.5),
+                prob_excess_right: Prob::new(0.5),
+                blend: BlendMethod::Select,
+            },
+            p_mutate_element: Prob::new(1.0),
+            weight_perturbance: WeightPerturbanceMethod::JiggleUniform {
+                range: WeightRange::bipolar(0.1),
+            },
+            self_adaptation: None,
+            mutate_weights: MutateMethodWeighting {
+                w_modify_weight: 1,
+                w_add_connection: 0,
+                w_enable_connection: 0,
+                w_delete_connection: 0,
+                w_delete_node: 0,
+                w_add_node: 0,
+                w_change_node_type: 0,
+                w_modify_node_params: 0,
+            },
+            mutation_schedule: MutationSchedule::single(),
+            bridge_deleted_nodes: false,
+            prune_offspring: None,
+            validate_offspring: true,
+            global_cache: cache,
+            element_strategy: strategy,
+            _n: PhantomData,
+        }
+    }
+
+    #[test]
+    fn test_discard_invalid_offspring() {
+        let switch = Arc::new(AtomicBool::new(true));
+        let genome = switched_genome(&switch, &switch);
+        let strategy = SwitchedStrategy(switch.clone());
+        let mut cache = GlobalInnovationCache::new();
+        let mut mater = switched_mater(&mut cache, &strategy);
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let fitness = Fitness::new(1.0);
+
+        let mut operators = Vec::new();
+        let (offspring, contributors) =
+            mater.mate_traced(&genome, &genome, fitness, fitness, false, &mut operators, &mut rng);
+        assert_eq!(Contributors::Left, contributors);
+        assert_eq!(1, operators.len());
+        assert!(!Genome::diff(&genome, &offspring).is_empty());
+
+        // the mutated copy is invalid now, so it is replaced by the unmodified parent.
+        switch.store(false, Ordering::SeqCst);
+        let mut operators = Vec::new();
+        let (offspring, contributors) =
+            mater.mate_traced(&genome, &genome, fitness, fitness, false, &mut operators, &mut rng);
+        assert_eq!(Contributors::Left, contributors);
+        assert!(operators.is_empty());
+        assert!(Genome::diff(&genome, &offspring).is_empty());
+    }
+
+    #[test]
+    fn test_mutate_either_reports_copied_parent() {
+        let switch = Arc::new(AtomicBool::new(true));
+        let genome = switched_genome(&switch, &switch);
+        let mut unlinked = Genome::new();
+        unlinked.add_node(NodeInnovation(0), Switched(switch.clone()));
+        unlinked.add_node(NodeInnovation(1), Switched(switch.clone()));
+        let strategy = SwitchedStrategy(switch.clone());
+        let mut cache = GlobalInnovationCache::new();
+        let mut mater = switched_mater(&mut cache, &strategy);
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let fitness = Fitness::new(1.0);
+
+        // there is no link weight to mutate in the left parent, so the right one is mutated.
+        let mut operators = Vec::new();
+        let (offspring, contributors) = mater.mate_traced(&unlinked,
+                                                          &genome,
+                                                          fitness,
+                                                          fitness,
+                                                          false,
+                                                          &mut operators,
+                                                          &mut rng);
+        assert_eq!(Contributors::Right, contributors);
+        assert_eq!(vec![Operator::Mutate(MutateMethod::ModifyWeight)], operators);
+        assert_eq!(1, offspring.link_count());
+    }
+
+    #[test]
+    fn test_mutation_count_zero() {
+        let switch = Arc::new(AtomicBool::new(true));
+        let genome = switched_genome(&switch, &switch);
+        let strategy = SwitchedStrategy(switch.clone());
+        let mut cache = GlobalInnovationCache::new();
+        let mut mater = switched_mater(&mut cache, &strategy);
+        mater.mutation_schedule = MutationSchedule {
+            count: MutationCount::Fixed(0),
+            mutate_after_crossover: true,
+        };
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let fitness = Fitness::new(1.0);
+
+        let mut operators = Vec::new();
+        let (offspring, contributors) =
+            mater.mate_traced(&genome, &genome, fitness, fitness, false, &mut operators, &mut rng);
+        assert_eq!(Contributors::Left, contributors);
+        assert!(operators.is_empty());
+        assert!(Genome::diff(&genome, &offspring).is_empty());
+
+        mater.p_crossover = Prob::new(1.0);
+        let mut operators = Vec::new();
+        let (offspring, contributors) =
+            mater.mate_traced(&genome, &genome, fitness, fitness, false, &mut operators, &mut rng);
+        assert_eq!(Contributors::Both, contributors);
+        assert_eq!(vec![Operator::Crossover], operators);
+        assert!(Genome::diff(&genome, &offspring).is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let i0 = NodeInnovation(0);
+        let h = NodeInnovation(1);
+        let o = NodeInnovation(2);
+        let x = NodeInnovation(3);
+        let y = NodeInnovation(4);
+
+        let mut old_genome = Genome::<Neuron>::new();
+        old_genome.add_node(i0, Neuron::Input);
+        old_genome.add_node(h, Neuron::Hidden);
+        old_genome.add_node(o, Neuron::Output);
+        old_genome.add_node(x, Neuron::Hidden);
+        old_genome.add_link(i0, h, LinkInnovation(0), Weight(0.5));
+        old_genome.add_link(h, o, LinkInnovation(1), Weight(0.5));
+        old_genome.add_link_with_active(i0, o, LinkInnovation(2), Weight(0.5), false);
+
+        let mut new_genome = Genome::<Neuron>::new();
+        new_genome.add_node(i0, Neuron::Input);
+        new_genome.add_node(h, Neuron::Hidden);
+        new_genome.add_node(o, Neuron::Output);
+        new_genome.add_node(y, Neuron::Hidden);
+        new_genome.add_link_with_active(h, o, LinkInnovation(1), Weight(0.5), false);
+        new_genome.add_link(i0, o, LinkInnovation(2), Weight(0.75));
+        new_genome.add_link(i0, y, LinkInnovation(3), Weight(0.5));
+        new_genome.node_params.insert(o,
+                                      NodeParams {
+                                          bias: Weight(0.5),
+                                          gain: Weight(1.0),
+                                      });
+
+        assert!(Genome::diff(&old_genome, &old_genome).is_empty());
+
+        assert_eq!(GenomeDiff {
+                       added_nodes: vec![y],
+                       removed_nodes: vec![x],
+                       added_links: vec![LinkInnovation(3)],
+                       removed_links: vec![LinkInnovation(0)],
+                       enabled_links: vec![LinkInnovation(2)],
+                       disabled_links: vec![LinkInnovation(1)],
+                       weight_deltas: vec![(LinkInnovation(2), 0.25)],
+                       changed_node_types: vec![],
+                       node_param_deltas: vec![(o,
+                                                NodeParams {
+                                                    bias: Weight(0.5),
+                                                    gain: Weight(0.0),
+                                                })],
+                   },
+                   Genome::diff(&old_genome, &new_genome));
+
+        // structurally identical, but a different node type.
+        let changed = old_genome.rebuild(|ni, nt| {
+            if ni == h {
+                Some(Neuron::Tanh)
+            } else {
+                Some(nt.clone())
+            }
+        });
+        let diff = Genome::diff(&old_genome, &changed);
+        assert!(!diff.is_empty());
+        assert_eq!(vec![h], diff.changed_node_types);
+        assert!(diff.node_param_deltas.is_empty());
+    }
+
+    #[test]
+    fn test_mutate_add_link() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let i0 = cache.create_node_innovation();
+        let o = cache.create_node_innovation();
+
+        let mut genome = Genome::<Neuron>::new();
+        genome.add_node(i0, Neuron::Input);
+        genome.add_node(o, Neuron::Output);
+
+        assert!(genome.mutate_add_link(Weight(0.5), &mut cache, &mut rng));
+        assert_eq!(1, genome.link_count());
+        assert_eq!(Ok(()), genome.validate());
+
+        // fully connected
+        assert!(!genome.mutate_add_link(Weight(0.5), &mut cache, &mut rng));
+        assert_eq!(1, genome.link_count());
+    }
+
+    #[test]
+    fn test_mutate_add_link_reenables_disabled_link() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let i0 = cache.create_node_innovation();
+        let o = cache.create_node_innovation();
+        let link = cache.get_or_create_link_innovation(i0, o);
+
+        let mut genome = Genome::<Neuron>::new();
+        genome.add_node(i0, Neuron::Input);
+        genome.add_node(o, Neuron::Output);
+        genome.add_link_with_active(i0, o, link, Weight(0.25), false);
+        let disabled_genome = genome.clone();
+
+        assert!(genome.mutate_add_link(Weight(0.5), &mut cache, &mut rng));
+        assert_eq!(1, genome.link_count());
+        assert_eq!(Ok(()), genome.validate());
+
+        let diff = Genome::diff(&disabled_genome, &genome);
+        assert_eq!(vec![link], diff.enabled_links);
+        assert!(diff.added_links.is_empty());
+        assert!(diff.weight_deltas.is_empty());
+    }
+
+    #[test]
+    fn test_mutate_add_link_only_reenables_picked_pair() {
+        let mut cache = GlobalInnovationCache::new();
+        let i0 = cache.create_node_innovation();
+        let i1 = cache.create_node_innovation();
+        let h = cache.create_node_innovation();
+        let o0 = cache.create_node_innovation();
+        let o1 = cache.create_node_innovation();
+
+        let mut genome = Genome::<Neuron>::new();
+        genome.add_node(i0, Neuron::Input);
+        genome.add_node(i1, Neuron::Input);
+        genome.add_node(h, Neuron::Hidden);
+        genome.add_node(o0, Neuron::Output);
+        genome.add_node(o1, Neuron::Output);
+
+        // connect every valid pair, but leave `i1 -> o1` disabled.
+        for &(source, target) in &[(i0, h), (i1, h), (h, o0), (h, o1), (i0, o0), (i0, o1),
+                                   (i1, o0)] {
+            genome.add_link(source,
+                            target,
+                            cache.get_or_create_link_innovation(source, target),
+                            Weight(0.5));
+        }
+        let disabled_link = cache.get_or_create_link_innovation(i1, o1);
+        genome.add_link_with_active(i1, o1, disabled_link, Weight(0.25), false);
+        let disabled_genome = genome.clone();
+
+        for seed in 1..20 {
+            let mut rng = XorShiftRng::from_seed([seed, 2, 3, 4]);
+            let mut genome = disabled_genome.clone();
+            assert!(genome.mutate_add_link(Weight(1.0), &mut cache, &mut rng));
+            assert_eq!(8, genome.link_count());
+            assert_eq!(Ok(()), genome.validate());
+
+            let diff = Genome::diff(&disabled_genome, &genome);
+            assert_eq!(vec![disabled_link], diff.enabled_links);
+            assert!(diff.added_links.is_empty());
+            assert!(diff.weight_deltas.is_empty());
+
+            // all pairs are actively connected now, so nothing is left to add.
+            let full_genome = genome.clone();
+            assert!(!genome.mutate_add_link(Weight(1.0), &mut cache, &mut rng));
+            assert!(Genome::diff(&full_genome, &genome).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_mutate_add_node_shares_split_innovation() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let i0 = cache.create_node_innovation();
+        let o = cache.create_node_innovation();
+
+        let mut genome = Genome::<Neuron>::new();
+        genome.add_node(i0, Neuron::Input);
+        genome.add_node(o, Neuron::Output);
+        genome.add_link(i0, o, cache.get_or_create_link_innovation(i0, o), Weight(0.5));
+
+        let mut left = genome.clone();
+        let mut right = genome.clone();
+        assert!(left.mutate_add_node(Neuron::Hidden, Weight(1.0), &mut cache, &mut rng));
+        assert!(right.mutate_add_node(Neuron::Hidden, Weight(1.0), &mut cache, &mut rng));
+
+        // both genomes split the same link and are structurally identical.
+        let left_diff = Genome::diff(&genome, &left);
+        assert_eq!(1, left_diff.added_nodes.len());
+        assert_eq!(2, left_diff.added_links.len());
+        assert_eq!(left_diff, Genome::diff(&genome, &right));
+        assert!(Genome::diff(&left, &right).is_empty());
+
+        // after a reset, the same split creates a new node.
+        cache.reset_split_node_innovations();
+        let mut next = genome.clone();
+        assert!(next.mutate_add_node(Neuron::Hidden, Weight(1.0), &mut cache, &mut rng));
+        assert!(Genome::diff(&genome, &next).added_nodes != left_diff.added_nodes);
+    }
+
+    /// Creates link innovations for all pairs of `nodes`, starting with source node `offset`.
+    /// Returns them sorted by node pair.
+
+    fn link_innovations_of_all_pairs(mut cache: &ConcurrentInnovationCache,
+                                     nodes: &[NodeInnovation],
+                                     offset: usize)
+                                     -> Vec<((NodeInnovation, NodeInnovation), LinkInnovation)> {
+        let mut links = Vec::new();
+        for i in 0..nodes.len() {
+            let source = nodes[(i + offset) % nodes.len()];
+            for &target in nodes.iter() {
+                links.push(((source, target), cache.get_or_create_link_innovation(source, target)));
+            }
+        }
+        links.sort();
+        links
+    }
+
+    #[test]
+    fn test_concurrent_innovation_cache() {
+        let shared = Arc::new(ConcurrentInnovationCache::new());
+        let nodes: Vec<NodeInnovation> = {
+            let mut cache: &ConcurrentInnovationCache = &shared;
+            (0..4).map(|_| cache.create_node_innovation()).collect()
+        };
+
+        // each thread visits the node pairs in a different order.
+        let threads: Vec<_> = (0..4)
+                                  .map(|t| {
+                                      let shared = shared.clone();
+                                      let nodes = nodes.clone();
+                                      thread::spawn(move || {
+                                          link_innovations_of_all_pairs(&shared, &nodes, t)
+                                      })
+                                  })
+                                  .collect();
+
+        let results: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        assert_eq!(16, results[0].len());
+        for links in results.iter() {
+            assert_eq!(&results[0], links);
+        }
+
+        let mut cache = Arc::try_unwrap(shared).ok().unwrap().into_inner();
+        let (source, target) = (nodes[1], nodes[2]);
+        let link = results[0].iter().find(|&&(pair, _)| pair == (source, target)).unwrap().1;
+        assert_eq!(link, cache.get_or_create_link_innovation(source, target));
+    }
+
+    #[test]
+    fn test_register_genome() {
+        let mut cache = GlobalInnovationCache::new();
+        let genome = input_hidden_output(&mut cache);
+        let links: Vec<_> = {
+            let mut links = Vec::new();
+            genome.network.each_link_ref(|link_ref| {
+                let source_node: NodeInnovation = link_ref.external_source_node_id().into();
+                let target_node: NodeInnovation = link_ref.external_target_node_id().into();
+                let link_innovation: LinkInnovation = link_ref.external_link_id().into();
+                links.push((source_node, target_node, link_innovation));
+            });
+            links
+        };
+        assert!(links.len() > 0);
+
+        // a new run starts with a fresh cache.
+        let mut new_cache = GlobalInnovationCache::new();
+        new_cache.register_genome(&genome);
+        assert_eq!(format!("{:?}", cache), format!("{:?}", new_cache));
+
+        for &(source_node, target_node, link_innovation) in links.iter() {
+            assert_eq!(link_innovation,
+                       new_cache.get_or_create_link_innovation(source_node, target_node));
+        }
+
+        let node = new_cache.create_node_innovation();
+        assert!(!genome.has_node(node));
+        let link = new_cache.get_or_create_link_innovation(node, links[0].1);
+        assert!(links.iter().all(|&(_, _, link_innovation)| link_innovation != link));
+
+        // known pairs are kept.
+        let (source_node, target_node, link_innovation) = links[0];
+        let mut other = Genome::<Neuron>::new();
+        other.add_node(source_node, Neuron::Input);
+        other.add_node(target_node, Neuron::Hidden);
+        other.add_link(source_node, target_node, LinkInnovation(100), Weight(0.5));
+        new_cache.register_genome(&other);
+        assert_eq!(link_innovation,
+                   new_cache.get_or_create_link_innovation(source_node, target_node));
+        assert_eq!(LinkInnovation(101),
+                   new_cache.get_or_create_link_innovation(target_node, node));
+    }
+
+    #[test]
+    fn test_mutate_link_weights_self_adaptive() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let range = WeightRange::bipolar(1.0);
+        let adaptation = SelfAdaptation {
+            initial_sigma: 0.1,
+            tau_global: 0.2,
+            tau_local: 0.1,
+            min_sigma: 0.01,
+            per_link: true,
+        };
+
+        let mut genome = input_hidden_output(&mut cache);
+        assert!(genome.step_sizes().is_none());
+
+        for _ in 0..100 {
+            assert!(genome.mutate_link_weights_self_adaptive(Prob::new(0.5),
+                                                             &adaptation,
+                                                             &range,
+                                                             &mut rng) > 0);
+            let step_sizes = genome.step_sizes().unwrap();
+            assert!(step_sizes.sigma >= adaptation.min_sigma);
+            assert!(step_sizes.link_sigma(LinkInnovation(0)) >= adaptation.min_sigma);
+            genome.visit_active_links(|_, _, weight| assert!(range.in_range(weight)));
+        }
+
+        // step sizes are inherited and averaged
+        let mut left = genome.clone();
+        left.set_step_sizes(Some(StepSizes::new(0.1)));
+        let mut right = genome.clone();
+        right.set_step_sizes(Some(StepSizes::new(0.3)));
+        let c = ProbabilisticCrossover {
+            prob_match_left: Prob::new(0.5),
+            prob_disjoint_left: Prob::new(1.0),
+            prob_excess_left: Prob::new(1.0),
+            prob_disjoint_right: Prob::new(0.0),
+            prob_excess_right: Prob::new(0.0),
+            blend: BlendMethod::Select,
+        };
+        let offspring = Genome::crossover(&left, &right, &c, &range, &range, &range, &mut rng);
+        assert!((offspring.step_sizes().unwrap().sigma - 0.2).abs() < 1e-9);
+
+        let link_sigma = genome.step_sizes().unwrap().link_sigma(LinkInnovation(0));
+        let offspring = Genome::crossover(&left, &genome, &c, &range, &range, &range, &mut rng);
+        assert!((offspring.step_sizes().unwrap().link_sigma(LinkInnovation(0)) -
+                 0.5 * (0.1 + link_sigma))
+                    .abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_self_adaptive_common_noise_applied_once() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut cache = GlobalInnovationCache::new();
+        let range = WeightRange::bipolar(1.0);
+        // without local noise, each step size is multiplied by the same factor.
+        let adaptation = SelfAdaptation {
+            initial_sigma: 0.1,
+            tau_global: 0.2,
+            tau_local: 0.0,
+            min_sigma: 0.001,
+            per_link: true,
+        };
+
+        let mut genome = input_hidden_output(&mut cache);
+        genome.set_step_sizes(Some(StepSizes::new(0.1)));
+
+        let common_noise = Normal::new(0.0, 1.0).ind_sample(&mut rng.clone());
+        let factor = (adaptation.tau_global * common_noise).exp();
+        assert!((factor - 1.0).abs() > 0.01);
+
+        assert_eq!(2,
+                   genome.mutate_link_weights_self_adaptive(Prob::new(1.0),
+                                                            &adaptation,
+                                                            &range,
+                                                            &mut rng));
+        let step_sizes = genome.step_sizes().unwrap();
+        assert!((step_sizes.sigma - 0.1 * factor).abs() < 1e-12);
+        assert!((step_sizes.link_sigma(LinkInnovation(0)) - 0.1 * factor).abs() < 1e-12);
+        assert!((step_sizes.link_sigma(LinkInnovation(1)) - 0.1 * factor).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_crossover_average() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let range = WeightRange::bipolar(1.0);
+
+        let left = input_hidden_output(&mut cache);
+        let mut right = left.clone();
+        right.mutate_link_weights_uniformly(Prob::new(1.0),
+                                            &WeightPerturbanceMethod::Random,
+                                            &range,
+                                            &mut rng);
+
+        let c = ProbabilisticCrossover {
+            prob_match_left: Prob::new(0.5),
+            prob_disjoint_left: Prob::new(1.0),
+            prob_excess_left: Prob::new(1.0),
+            prob_disjoint_right: Prob::new(0.0),
+            prob_excess_right: Prob::new(0.0),
+            blend: BlendMethod::Average,
+        };
+        let offspring = Genome::crossover(&left, &right, &c, &range, &range, &range, &mut rng);
+
+        let mut left_weights = Vec::new();
+        left.visit_active_links(|_, _, w| left_weights.push(w.0));
+        let mut right_weights = Vec::new();
+        right.visit_active_links(|_, _, w| right_weights.push(w.0));
+        let mut offspring_weights = Vec::new();
+        offspring.visit_active_links(|_, _, w| offspring_weights.push(w.0));
+
+        assert_eq!(2, offspring_weights.len());
+        for i in 0..2 {
+            assert!((offspring_weights[i] - 0.5 * (left_weights[i] + right_weights[i])).abs() <
+                    1e-9);
+        }
+    }
+
+    #[test]
+    fn test_crossover_blx_alpha_in_range() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut cache = GlobalInnovationCache::new();
+        let weight_range = WeightRange::bipolar(1.0);
+        let bias_range = WeightRange::new(0.5, -0.5);
+        let gain_range = WeightRange::new(2.0, 0.5);
+
+        let mut left = input_hidden_output(&mut cache);
+        let mut right = left.clone();
+        for genome in &mut [&mut left, &mut right] {
+            genome.mutate_link_weights_uniformly(Prob::new(1.0),
+                                                 &WeightPerturbanceMethod::Random,
+                                                 &weight_range,
+                                                 &mut rng);
+            genome.mutate_node_params_uniformly(Prob::new(1.0),
+                                                &WeightPerturbanceMethod::Random,
+                                                &bias_range,
+                                                &gain_range,
+                                                &mut rng);
+        }
+
+        let c = ProbabilisticCrossover {
+            prob_match_left: Prob::new(0.5),
+            prob_disjoint_left: Prob::new(1.0),
+            prob_excess_left: Prob::new(1.0),
+            prob_disjoint_right: Prob::new(0.0),
+            prob_excess_right: Prob::new(0.0),
+            blend: BlendMethod::BlxAlpha { alpha: 2.0 },
+        };
+
+        for _ in 0..100 {
+            let offspring = Genome::crossover(&left,
+                                              &right,
+                                              &c,
+                                              &weight_range,
+                                              &bias_range,
+                                              &gain_range,
+                                              &mut rng);
+            offspring.visit_active_links(|_, _, w| assert!(weight_range.in_range(w)));
+            offspring.visit_nodes(|ni, _| {
+                let params = offspring.node_params(ni).unwrap();
+                assert!(bias_range.in_range(params.bias));
+                assert!(gain_range.in_range(params.gain));
+            });
+        }
+    }
+
+    #[test]
+    fn test_crossover_symmetric() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let range = WeightRange::bipolar(1.0);
+
+        let left = input_hidden_output(&mut cache);
+        let mut right = left.clone();
+        let i0 = NodeInnovation(0);
+        let o = NodeInnovation(2);
+        right.add_link(i0, o, cache.get_or_create_link_innovation(i0, o), Weight(0.5));
+
+        let c = ProbabilisticCrossover {
+            prob_match_left: Prob::new(1.0),
+            prob_disjoint_left: Prob::new(1.0),
+            prob_excess_left: Prob::new(1.0),
+            prob_disjoint_right: Prob::new(0.0),
+            prob_excess_right: Prob::new(0.0),
+            blend: BlendMethod::Select,
+        };
+
+        // the excess link of the less fit parent is never inherited
+        assert_eq!(2,
+                   Genome::crossover(&left, &right, &c, &range, &range, &range, &mut rng)
+                       .link_count());
+
+        // for parents of equal fitness, it is inherited like those of the left parent
+        assert_eq!(3,
+                   Genome::crossover(&left, &right, &c.symmetric(), &range, &range, &range,
+                                     &mut rng)
+                       .link_count());
+    }
+
+    #[test]
+    fn test_mutate_change_node_type() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+
+        let mut genome = input_hidden_output(&mut cache);
+
+        // output nodes do not accept outgoing links, so they can't replace a hidden node.
+        assert!(!genome.mutate_change_node_type(Neuron::Output, &mut rng));
+
+        // the only hidden node already has this type.
+        let unchanged = genome.clone();
+        assert!(!genome.mutate_change_node_type(Neuron::Hidden, &mut rng));
+        assert_eq!(0, Genome::node_type_mismatches(&unchanged, &genome));
+
+        assert!(genome.mutate_change_node_type(Neuron::Tanh, &mut rng));
+        assert_eq!(3, genome.node_count());
+        assert_eq!(2, genome.link_count());
+        assert_eq!(1, Genome::node_type_mismatches(&unchanged, &genome));
+        assert!(!genome.mutate_change_node_type(Neuron::Tanh, &mut rng));
+
+        let mut genome = Genome::<Neuron>::new();
+        genome.add_node(cache.create_node_innovation(), Neuron::Input);
+        genome.add_node(cache.create_node_innovation(), Neuron::Output);
+        assert!(!genome.mutate_change_node_type(Neuron::Hidden, &mut rng));
+    }
+
+    #[test]
+    fn test_genome_builder() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let range = WeightRange::bipolar(1.0);
+
+        let builder = GenomeBuilder::new(vec![Neuron::Input, Neuron::Input],
+                                         vec![Neuron::Output, Neuron::Output],
+                                         Some(Neuron::Input),
+                                         &mut cache);
+
+        let unconnected = builder.build(InitialConnectivity::Unconnected, &range, &mut cache, &mut rng);
+        assert_eq!(5, unconnected.node_count());
+        assert_eq!(0, unconnected.link_count());
+
+        let full1 = builder.build(InitialConnectivity::Full, &range, &mut cache, &mut rng);
+        let full2 = builder.build(InitialConnectivity::Full, &range, &mut cache, &mut rng);
+        assert_eq!(5, full1.node_count());
+        assert_eq!(6, full1.link_count());
+        full1.visit_active_links(|_, _, weight| assert!(range.in_range(weight)));
+
+        // both genomes share all node and link innovations.
+        let m = Genome::combined_alignment_metric(&full1, &full2);
+        assert_eq!(5, m.node_metric.matching);
+        assert_eq!(6, m.link_metric.matching);
+
+        let sparse = builder.build(InitialConnectivity::Sparse(Prob::new(0.0)), &range, &mut cache, &mut rng);
+        assert_eq!(0, sparse.link_count());
+    }
+
+    #[test]
+    fn test_persist() {
+        let mut cache = GlobalInnovationCache::new();
+        let mut genome = input_hidden_output(&mut cache);
+        let i0 = NodeInnovation(0);
+        let h = NodeInnovation(1);
+        let o = NodeInnovation(2);
+        genome.add_link_with_active(i0,
+                                    o,
+                                    cache.get_or_create_link_innovation(i0, o),
+                                    Weight(-0.1),
+                                    false);
+        genome.node_params.insert(h,
+                                  NodeParams {
+                                      bias: Weight(0.3),
+                                      gain: Weight(1.0 / 3.0),
+                                  });
+        let mut step_sizes = StepSizes::new(0.2);
+        step_sizes.link_sigmas.insert(LinkInnovation(1), 0.05);
+        genome.set_step_sizes(Some(step_sizes));
+
+        let mut buf = Vec::new();
+        save(&genome, &mut buf).unwrap();
+        let archive = String::from_utf8(buf).unwrap();
+        let restored: Genome<Neuron> = load(&archive).unwrap();
+        assert_eq!(format!("{:?}", genome), format!("{:?}", restored));
+
+        let duplicate_node = archive.replace("(node 2 Output ", "(node 1 Output ");
+        assert!(duplicate_node != archive);
+        assert!(load::<Genome<Neuron>>(&duplicate_node).is_err());
+
+        let unknown_node = archive.replace("(link 2 0 2 ", "(link 2 0 7 ");
+        assert!(unknown_node != archive);
+        assert!(load::<Genome<Neuron>>(&unknown_node).is_err());
+    }
 }
@@ -1 +1,177 @@
 pub mod acyclic_network;
+pub mod recurrent_network;
+
+use acyclic_network::NodeType;
+use traits::Genotype;
+use fitness::Fitness;
+use lineage::{Operator, Contributors};
+use mutate::{MutateMethod, MutateMethodWeighting, MutationSchedule};
+use crossover::ProbabilisticCrossover;
+use prob::Prob;
+use rand::Rng;
+
+/// The role a node plays when a genome is evaluated as a network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeRole {
+    /// Receives one value of the input vector.
+    Input,
+    /// Constantly emits 1.0.
+    Bias,
+    Hidden,
+    /// Contributes one value to the output vector.
+    Output,
+}
+
+/// Node types which can be evaluated. Each node knows it's role within the network and
+/// applies an activation function to the weighted sum of it's inputs.
+
+pub trait ActivationNodeType: NodeType {
+    fn node_role(&self) -> NodeRole;
+    fn activate(&self, input: f64) -> f64;
+}
+
+/// Node types which can be written to graph formats like GML or Graphviz DOT.
+
+pub trait NodeLabel {
+    /// A short, human readable name of the node type.
+    fn node_label(&self) -> String;
+}
+
+/// The genome specific part of a `Mater`.
+///
+/// The provided methods choose between crossover and mutation and apply the mutation schedule
+/// in the same way for all genome types. A `Mater` implements the required methods and
+/// forwards `Mate::mate_traced` to `mate_with`.
+
+pub trait GenomeMater<G: Genotype + Clone> {
+    /// Probability of producing an offspring by crossover instead of mutation.
+    fn p_crossover(&self) -> Prob;
+
+    fn p_crossover_detail(&self) -> ProbabilisticCrossover;
+
+    fn mutate_weights(&self) -> MutateMethodWeighting;
+
+    fn mutation_schedule(&self) -> MutationSchedule;
+
+    /// Applies `mutate_method` to `offspring`. Returns `true` if `offspring` was modified.
+    fn mutate<R: Rng>(&mut self,
+                      offspring: &mut G,
+                      mutate_method: MutateMethod,
+                      rng: &mut R)
+                      -> bool;
+
+    fn crossover<R: Rng>(&mut self,
+                         parent_left: &G,
+                         parent_right: &G,
+                         crossover: &ProbabilisticCrossover,
+                         rng: &mut R)
+                         -> G;
+
+    /// Called with each finished offspring, e.g. to prune or validate it. Returns `false` if the
+    /// offspring has to be discarded. Accepts every offspring by default.
+    fn finish_offspring(&mut self, _offspring: &mut G) -> bool {
+        true
+    }
+
+    /// Applies `steps` randomly chosen mutations to `offspring`. Each mutation which modified
+    /// `offspring` is appended to `operators`.
+
+    fn mutate_steps<R: Rng>(&mut self,
+                            offspring: &mut G,
+                            steps: usize,
+                            operators: &mut Vec<Operator>,
+                            rng: &mut R) {
+        for _ in 0..steps {
+            let mutate_method = MutateMethod::random_with(&self.mutate_weights(), rng);
+            if self.mutate(offspring, mutate_method, rng) {
+                operators.push(Operator::Mutate(mutate_method));
+            }
+        }
+    }
+
+    /// Mutates a copy of `parent_left`. If this does not modify the genome, a copy of
+    /// `parent_right` is mutated instead. Returns the offspring together with the parent it was
+    /// copied from.
+
+    fn mutate_either<R: Rng>(&mut self,
+                             parent_left: &G,
+                             parent_right: &G,
+                             operators: &mut Vec<Operator>,
+                             rng: &mut R)
+                             -> (G, Contributors) {
+        let mutate_method = MutateMethod::random_with(&self.mutate_weights(), rng);
+
+        {
+            let mut offspring = parent_left.clone();
+
+            if self.mutate(&mut offspring, mutate_method, rng) {
+                operators.push(Operator::Mutate(mutate_method));
+                return (offspring, Contributors::Left);
+            }
+        }
+
+        info!("no change in mutate left genome. mutate_method: {:?}", mutate_method);
+
+        let mut offspring = parent_right.clone();
+
+        if self.mutate(&mut offspring, mutate_method, rng) {
+            operators.push(Operator::Mutate(mutate_method));
+        } else {
+            info!("no change in mutate right genome");
+        }
+
+        (offspring, Contributors::Right)
+    }
+
+    /// Produces an offspring of `parent_left` and `parent_right`, either by crossover or by
+    /// mutation. See `Mate::mate_traced`.
+    ///
+    /// If `finish_offspring` discards the offspring, an unmodified copy of `parent_left` is
+    /// returned instead and no operators are recorded. Returns the offspring together with the
+    /// parents which contributed to it.
+
+    fn mate_with<R: Rng>(&mut self,
+                         parent_left: &G,
+                         parent_right: &G,
+                         fitness_left: Fitness,
+                         fitness_right: Fitness,
+                         prefer_mutate: bool,
+                         operators: &mut Vec<Operator>,
+                         rng: &mut R)
+                         -> (G, Contributors) {
+        let schedule = self.mutation_schedule();
+        let num_operators = operators.len();
+
+        let (mut offspring, contributors) = if prefer_mutate == false && self.p_crossover().flip(rng) {
+            // Parents of equal fitness contribute their genes equally.
+            let crossover = if fitness_left == fitness_right {
+                self.p_crossover_detail().symmetric()
+            } else {
+                self.p_crossover_detail()
+            };
+            let mut offspring = self.crossover(parent_left, parent_right, &crossover, rng);
+            operators.push(Operator::Crossover);
+            if schedule.mutate_after_crossover {
+                let steps = schedule.count.sample(rng);
+                self.mutate_steps(&mut offspring, steps, operators, rng);
+            }
+            (offspring, Contributors::Both)
+        } else {
+            let steps = schedule.count.sample(rng);
+            if steps == 0 {
+                (parent_left.clone(), Contributors::Left)
+            } else {
+                let (mut offspring, contributors) =
+                    self.mutate_either(parent_left, parent_right, operators, rng);
+                self.mutate_steps(&mut offspring, steps - 1, operators, rng);
+                (offspring, contributors)
+            }
+        };
+
+        if !self.finish_offspring(&mut offspring) {
+            operators.truncate(num_operators);
+            return (parent_left.clone(), Contributors::Left);
+        }
+        (offspring, contributors)
+    }
+}
@@ -0,0 +1,1653 @@
+use traits::{Distance, Genotype, Mate};
+use fitness::Fitness;
+use lineage::{Operator, Contributors};
+use weight::{Weight, WeightRange, WeightPerturbanceMethod};
+use alignment_metric::AlignmentMetric;
+use alignment::{Alignment, align_sorted_iterators, LeftOrRight};
+use crossover::ProbabilisticCrossover;
+use prob::Prob;
+use mutate::{MutateMethod, MutateMethodWeighting, MutationSchedule};
+use genomes::{ActivationNodeType, NodeRole, GenomeMater};
+pub use genomes::acyclic_network::{NodeType, NodeInnovation, LinkInnovation, GlobalCache,
+                                   GenomeDistance, ElementStrategy};
+use std::collections::{BTreeMap, BTreeSet};
+use std::marker::PhantomData;
+use std::cmp;
+use rand::Rng;
+use persist::{Persist, Reader, PersistError};
+use std::io::{self, Write};
+
+/// A link gene of a recurrent genome.
+#[derive(Clone, Copy, Debug)]
+pub struct LinkGene {
+    source_node: NodeInnovation,
+    target_node: NodeInnovation,
+    weight: Weight,
+    active: bool,
+}
+
+impl LinkGene {
+    pub fn source_node(&self) -> NodeInnovation {
+        self.source_node
+    }
+
+    pub fn target_node(&self) -> NodeInnovation {
+        self.target_node
+    }
+
+    pub fn weight(&self) -> Weight {
+        self.weight
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+/// Evolvable numeric parameters of a node gene of a recurrent genome.
+///
+/// In each time step, a node moves it's activation towards `activate(gain * sum + bias)` by
+/// `1 / time_constant` of the difference, where `sum` is the weighted sum of it's incoming
+/// links. With a time constant of 1.0 the activation is replaced in each step, larger time
+/// constants make the node respond more slowly. Parameters of input nodes have no effect.
+#[derive(Debug, Clone, Copy)]
+pub struct NodeParams {
+    pub bias: Weight,
+    pub gain: Weight,
+    pub time_constant: Weight,
+}
+
+impl NodeParams {
+    /// Creates neutral parameters, i.e. a bias of 0.0, a gain of 1.0 and a time constant of
+    /// 1.0.
+
+    pub fn new() -> Self {
+        NodeParams {
+            bias: Weight(0.0),
+            gain: Weight(1.0),
+            time_constant: Weight(1.0),
+        }
+    }
+
+    /// The sum of the absolute differences of all parameters.
+
+    pub fn distance(&self, other: &NodeParams) -> f64 {
+        (self.bias.0 - other.bias.0).abs() + (self.gain.0 - other.gain.0).abs() +
+        (self.time_constant.0 - other.time_constant.0).abs()
+    }
+}
+
+/// The ranges of all node parameters.
+#[derive(Debug, Clone, Copy)]
+pub struct NodeParamRanges {
+    pub bias: WeightRange,
+    pub gain: WeightRange,
+    pub time_constant: WeightRange,
+}
+
+impl NodeParamRanges {
+    /// Takes the ranges from `element_strategy`.
+
+    pub fn from_strategy<NT, S>(element_strategy: &S) -> Self
+        where NT: NodeType,
+              S: ElementStrategy<NT>
+    {
+        NodeParamRanges {
+            bias: element_strategy.node_bias_range(),
+            gain: element_strategy.node_gain_range(),
+            time_constant: element_strategy.node_time_constant_range(),
+        }
+    }
+}
+
+/// Genome representing a recurrent network.
+///
+/// In contrast to `acyclic_network::Genome`, links are allowed to form cycles. This includes
+/// self-loops and links pointing back to nodes closer to the inputs. Links are only restricted
+/// by the node types (`accept_incoming_links` and `accept_outgoing_links`) and there can be at
+/// most one link between two nodes.
+///
+/// Both node and link genes are stored sorted by their innovation number.
+#[derive(Clone, Debug)]
+pub struct Genome<NT: NodeType> {
+    nodes: BTreeMap<NodeInnovation, NT>,
+    // kept in sync with `nodes`.
+    node_params: BTreeMap<NodeInnovation, NodeParams>,
+    links: BTreeMap<LinkInnovation, LinkGene>,
+}
+
+impl<NT: NodeType> Genotype for Genome<NT> {}
+
+impl<NT: NodeType> Genome<NT> {
+    pub fn new() -> Self {
+        Genome {
+            nodes: BTreeMap::new(),
+            node_params: BTreeMap::new(),
+            links: BTreeMap::new(),
+        }
+    }
+
+    pub fn visit_nodes<F>(&self, mut f: F)
+        where F: FnMut(NodeInnovation, NT)
+    {
+        for (&node_innovation, node_type) in self.nodes.iter() {
+            f(node_innovation, node_type.clone());
+        }
+    }
+
+    pub fn visit_active_links<F>(&self, mut f: F)
+        where F: FnMut(NodeInnovation, NodeInnovation, Weight)
+    {
+        for link in self.links.values() {
+            if link.active {
+                f(link.source_node, link.target_node, link.weight);
+            }
+        }
+    }
+
+    /// Returns the type of node `node_innovation` if it exists.
+
+    pub fn node_type(&self, node_innovation: NodeInnovation) -> Option<&NT> {
+        self.nodes.get(&node_innovation)
+    }
+
+    /// Returns the parameters of node `node_innovation` if it exists.
+
+    pub fn node_params(&self, node_innovation: NodeInnovation) -> Option<NodeParams> {
+        self.node_params.get(&node_innovation).cloned()
+    }
+
+    /// Returns the link gene associated with `link_innovation` if it exists.
+
+    pub fn link(&self, link_innovation: LinkInnovation) -> Option<&LinkGene> {
+        self.links.get(&link_innovation)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn link_count(&self) -> usize {
+        self.links.len()
+    }
+
+    pub fn has_node(&self, node_innovation: NodeInnovation) -> bool {
+        self.nodes.contains_key(&node_innovation)
+    }
+
+    /// Returns `true` if there is a link (active or not) from `source_node` to `target_node`.
+    ///
+    /// # Complexity
+    ///
+    /// O(m) where `m` is the number of links.
+
+    pub fn has_link(&self, source_node: NodeInnovation, target_node: NodeInnovation) -> bool {
+        self.links.values().any(|link| link.source_node == source_node && link.target_node == target_node)
+    }
+
+    /// Add a new node with external id `node_innovation` and of type `node_type`
+    /// to the genome. The node starts with neutral parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a node with the same innovation already exists in the genome.
+
+    pub fn add_node(&mut self, node_innovation: NodeInnovation, node_type: NT) {
+        self.add_node_with_params(node_innovation, node_type, NodeParams::new());
+    }
+
+    /// Same as `add_node`, but with `node_params`.
+
+    pub fn add_node_with_params(&mut self,
+                                node_innovation: NodeInnovation,
+                                node_type: NT,
+                                node_params: NodeParams) {
+        if self.nodes.contains_key(&node_innovation) {
+            panic!("Duplicate node_innovation");
+        }
+        self.nodes.insert(node_innovation, node_type);
+        self.node_params.insert(node_innovation, node_params);
+    }
+
+    /// Add a link between `source_node` and `target_node`. Associates the new
+    /// link with `link_innovation` and gives it `weight`.
+    ///
+    /// Cycles are allowed. Use `valid_link` to check if the link is allowed.
+    ///
+    /// # Panics
+    ///
+    /// If one of `source_node` or `target_node` does not exist.
+    ///
+    /// If a link with the same link innovation already exists.
+
+    pub fn add_link(&mut self,
+                    source_node: NodeInnovation,
+                    target_node: NodeInnovation,
+                    link_innovation: LinkInnovation,
+                    weight: Weight) {
+        self.add_link_with_active(source_node, target_node, link_innovation, weight, true);
+    }
+
+    pub fn add_link_with_active(&mut self,
+                                source_node: NodeInnovation,
+                                target_node: NodeInnovation,
+                                link_innovation: LinkInnovation,
+                                weight: Weight,
+                                active: bool) {
+        assert!(self.has_node(source_node) && self.has_node(target_node));
+        debug_assert!(self.valid_link(source_node, target_node));
+
+        if self.links.contains_key(&link_innovation) {
+            panic!("Duplicate link_innovation");
+        }
+
+        self.links.insert(link_innovation,
+                          LinkGene {
+                              source_node: source_node,
+                              target_node: target_node,
+                              weight: weight,
+                              active: active,
+                          });
+    }
+
+    /// Check if a link from `source_node` to `target_node` is allowed by the node types and does
+    /// not duplicate an existing link. Cycles are allowed.
+
+    pub fn valid_link(&self, source_node: NodeInnovation, target_node: NodeInnovation) -> bool {
+        match (self.nodes.get(&source_node), self.nodes.get(&target_node)) {
+            (Some(source_type), Some(target_type)) => {
+                source_type.accept_outgoing_links() && target_type.accept_incoming_links() &&
+                !self.has_link(source_node, target_node)
+            }
+            _ => false,
+        }
+    }
+
+    /// Counts the number of matching, disjoint and excess node innovation numbers between
+    /// `left_genome` and `right_genome`, and sums up the parameter distance of matching nodes.
+
+    pub fn node_alignment_metric(left_genome: &Self, right_genome: &Self) -> AlignmentMetric {
+        let mut node_metric = AlignmentMetric::new();
+        node_metric.max_len = cmp::max(left_genome.nodes.len(), right_genome.nodes.len());
+
+        align_sorted_iterators(left_genome.node_params.iter(),
+                               right_genome.node_params.iter(),
+                               |&(kl, _), &(kr, _)| Ord::cmp(kl, kr),
+                               |alignment| {
+                                   match alignment {
+                                       Alignment::Match((_, left_params), (_, right_params)) => {
+                                           node_metric.matching += 1;
+                                           node_metric.weight_distance +=
+                                               left_params.distance(right_params);
+                                       }
+                                       Alignment::Disjoint(..) => node_metric.disjoint += 1,
+                                       Alignment::Excess(..) => node_metric.excess += 1,
+                                   }
+                               });
+
+        node_metric
+    }
+
+    /// Counts the number of matching, disjoint and excess link innovation numbers between
+    /// `left_genome` and `right_genome`, and sums up the weight distance of matching links.
+    ///
+    /// As link genes are globally sorted by their innovation number, this is a single pass over
+    /// both link lists.
+
+    pub fn link_alignment_metric(left_genome: &Self, right_genome: &Self) -> AlignmentMetric {
+        let mut link_metric = AlignmentMetric::new();
+        link_metric.max_len = cmp::max(left_genome.links.len(), right_genome.links.len());
+
+        align_sorted_iterators(left_genome.links.iter(),
+                               right_genome.links.iter(),
+                               |&(kl, _), &(kr, _)| Ord::cmp(kl, kr),
+                               |alignment| {
+                                   match alignment {
+                                       Alignment::Match((_, left_link), (_, right_link)) => {
+                                           link_metric.matching += 1;
+                                           link_metric.weight_distance +=
+                                               (left_link.weight.0 - right_link.weight.0).abs();
+                                       }
+                                       Alignment::Disjoint(..) => link_metric.disjoint += 1,
+                                       Alignment::Excess(..) => link_metric.excess += 1,
+                                   }
+                               });
+
+        link_metric
+    }
+
+    /// Performs a crossover operation on the two genomes `left_genome` and `right_genome`,
+    /// producing a new offspring genome.
+    ///
+    /// Blended link weights and node parameters are kept within `link_weight_range` and
+    /// `node_ranges`.
+
+    pub fn crossover<R: Rng>(left_genome: &Self,
+                             right_genome: &Self,
+                             c: &ProbabilisticCrossover,
+                             link_weight_range: &WeightRange,
+                             node_ranges: &NodeParamRanges,
+                             rng: &mut R)
+                             -> Self {
+        let mut offspring = Genome::new();
+
+        Genome::crossover_nodes(left_genome, right_genome, &mut offspring, c, node_ranges, rng);
+        Genome::crossover_links(left_genome,
+                                right_genome,
+                                &mut offspring,
+                                c,
+                                link_weight_range,
+                                rng);
+
+        return offspring;
+    }
+
+    /// Crossover the nodes of `left_genome` and `right_genome`. So either take a node from the
+    /// left or the right, depending on randomness and `c`.
+
+    fn crossover_nodes<R: Rng>(left_genome: &Self,
+                               right_genome: &Self,
+                               offspring: &mut Self,
+                               c: &ProbabilisticCrossover,
+                               node_ranges: &NodeParamRanges,
+                               rng: &mut R) {
+        align_sorted_iterators(left_genome.nodes.iter(),
+                               right_genome.nodes.iter(),
+                               |&(kl, _), &(kr, _)| Ord::cmp(kl, kr),
+                               |node_alignment| {
+                                   let take = match node_alignment {
+                                       Alignment::Match((&ni, left_type), (_, right_type)) => {
+                                           let left_params = left_genome.node_params[&ni];
+                                           let right_params = right_genome.node_params[&ni];
+                                           let (nt, selected) = if c.prob_match_left.flip(rng) {
+                                               (left_type, left_params)
+                                           } else {
+                                               (right_type, right_params)
+                                           };
+                                           let params = NodeParams {
+                                               bias: Weight(c.blend.blend(left_params.bias.0, right_params.bias.0, selected.bias.0, &node_ranges.bias, rng)),
+                                               gain: Weight(c.blend.blend(left_params.gain.0, right_params.gain.0, selected.gain.0, &node_ranges.gain, rng)),
+                                               time_constant: Weight(c.blend.blend(left_params.time_constant.0, right_params.time_constant.0, selected.time_constant.0, &node_ranges.time_constant, rng)),
+                                           };
+                                           Some((ni, nt, params))
+                                       }
+                                       Alignment::Disjoint((&ni, nt), LeftOrRight::Left) => {
+                                           if c.prob_disjoint_left.flip(rng) { Some((ni, nt, left_genome.node_params[&ni])) } else { None }
+                                       }
+                                       Alignment::Disjoint((&ni, nt), LeftOrRight::Right) => {
+                                           if c.prob_disjoint_right.flip(rng) { Some((ni, nt, right_genome.node_params[&ni])) } else { None }
+                                       }
+                                       Alignment::Excess((&ni, nt), LeftOrRight::Left) => {
+                                           if c.prob_excess_left.flip(rng) { Some((ni, nt, left_genome.node_params[&ni])) } else { None }
+                                       }
+                                       Alignment::Excess((&ni, nt), LeftOrRight::Right) => {
+                                           if c.prob_excess_right.flip(rng) { Some((ni, nt, right_genome.node_params[&ni])) } else { None }
+                                       }
+                                   };
+
+                                   if let Some((ni, nt, params)) = take {
+                                       offspring.add_node_with_params(ni, nt.clone(), params);
+                                   }
+                               });
+    }
+
+    /// Crossover the links of `left_genome` and `right_genome`.
+    ///
+    /// As cycles are allowed, we only have to make sure that missing nodes are added and that
+    /// the link is valid for the node types.
+
+    fn crossover_links<R: Rng>(left_genome: &Self,
+                               right_genome: &Self,
+                               offspring: &mut Self,
+                               c: &ProbabilisticCrossover,
+                               link_weight_range: &WeightRange,
+                               rng: &mut R) {
+        // First pass.
+        //
+        // Take all matching links from both genomes. Both their source and target nodes
+        // are matching nodes and as such were already added by `crossover_nodes`.
+
+        align_sorted_iterators(left_genome.links.iter(),
+                               right_genome.links.iter(),
+                               |&(kl, _), &(kr, _)| Ord::cmp(kl, kr),
+                               |link_alignment| {
+                                   if let Alignment::Match((&li, left_link), (_, right_link)) =
+                                          link_alignment {
+                                       let link = if c.prob_match_left.flip(rng) {
+                                           left_link
+                                       } else {
+                                           right_link
+                                       };
+                                       let weight = c.blend.blend(left_link.weight.0,
+                                                                  right_link.weight.0,
+                                                                  link.weight.0,
+                                                                  link_weight_range,
+                                                                  rng);
+                                       offspring.add_link_with_active(link.source_node,
+                                                                      link.target_node,
+                                                                      li,
+                                                                      Weight(weight),
+                                                                      link.active);
+                                   }
+                               });
+
+        // Second pass.
+        //
+        // Crossover disjoint and excess links. Nodes might not exist yet.
+
+        align_sorted_iterators(left_genome.links.iter(),
+                               right_genome.links.iter(),
+                               |&(kl, _), &(kr, _)| Ord::cmp(kl, kr),
+                               |link_alignment| {
+                                   let (li, link, prob, parent) = match link_alignment {
+                                       Alignment::Match(..) => return,
+                                       Alignment::Disjoint((&li, link), LeftOrRight::Left) => {
+                                           (li, link, c.prob_disjoint_left, left_genome)
+                                       }
+                                       Alignment::Disjoint((&li, link), LeftOrRight::Right) => {
+                                           (li, link, c.prob_disjoint_right, right_genome)
+                                       }
+                                       Alignment::Excess((&li, link), LeftOrRight::Left) => {
+                                           (li, link, c.prob_excess_left, left_genome)
+                                       }
+                                       Alignment::Excess((&li, link), LeftOrRight::Right) => {
+                                           (li, link, c.prob_excess_right, right_genome)
+                                       }
+                                   };
+
+                                   if !prob.flip(rng) {
+                                       return;
+                                   }
+
+                                   // Add nodes in case they do not exist. We take the nodes from
+                                   // the genome the link belongs to.
+                                   for &node in &[link.source_node, link.target_node] {
+                                       if !offspring.has_node(node) {
+                                           offspring.add_node_with_params(node,
+                                                                          parent.nodes[&node].clone(),
+                                                                          parent.node_params[&node]);
+                                       }
+                                   }
+
+                                   if offspring.valid_link(link.source_node, link.target_node) {
+                                       offspring.add_link_with_active(link.source_node,
+                                                                      link.target_node,
+                                                                      li,
+                                                                      link.weight,
+                                                                      link.active);
+                                   }
+                               });
+    }
+
+    fn random_link_innovation<R, F>(&self, rng: &mut R, filter: F) -> Option<LinkInnovation>
+        where R: Rng,
+              F: Fn(&LinkGene) -> bool
+    {
+        let candidates: Vec<LinkInnovation> = self.links
+                                                  .iter()
+                                                  .filter(|&(_, link)| filter(link))
+                                                  .map(|(&li, _)| li)
+                                                  .collect();
+        rng.choose(&candidates).cloned()
+    }
+
+    /// Mutate the genome by enabling a random disabled link.
+    ///
+    /// Return `true` if the genome was modified. Otherwise `false`.
+
+    pub fn mutate_enable_link<R: Rng>(&mut self, rng: &mut R) -> bool {
+        match self.random_link_innovation(rng, |link| !link.active) {
+            Some(li) => {
+                self.links.get_mut(&li).unwrap().active = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Mutate the genome by adding a random valid link. The link may introduce a cycle or
+    /// connect a node to itself.
+    ///
+    /// If a disabled link between the chosen pair of nodes already exists, that link gene is
+    /// re-enabled instead, keeping it's innovation number and weight.
+    ///
+    /// Return `true` if the genome was modified. Otherwise `false`.
+
+    pub fn mutate_add_link<R, G>(&mut self, link_weight: Weight, cache: &mut G, rng: &mut R) -> bool
+        where R: Rng,
+              G: GlobalCache
+    {
+        let connected: BTreeSet<(NodeInnovation, NodeInnovation)> = self.links
+            .values()
+            .filter(|link| link.active)
+            .map(|link| (link.source_node, link.target_node))
+            .collect();
+
+        let mut candidates = Vec::new();
+        for (&source_node, source_type) in self.nodes.iter() {
+            if !source_type.accept_outgoing_links() {
+                continue;
+            }
+            for (&target_node, target_type) in self.nodes.iter() {
+                if target_type.accept_incoming_links() &&
+                   !connected.contains(&(source_node, target_node)) {
+                    candidates.push((source_node, target_node));
+                }
+            }
+        }
+
+        match rng.choose(&candidates).cloned() {
+            Some((source_node, target_node)) => {
+                let disabled_link = self.links.values_mut().find(|link| {
+                    link.source_node == source_node && link.target_node == target_node
+                });
+
+                match disabled_link {
+                    Some(link) => {
+                        link.active = true;
+                    }
+                    None => {
+                        let link_innovation = cache.get_or_create_link_innovation(source_node,
+                                                                                  target_node);
+                        self.add_link(source_node, target_node, link_innovation, link_weight);
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Choose a random active link. Split it in half creating a globally new node innovation!
+    ///
+    /// Note that the new `node_type` should allow incoming and outgoing links! Otherwise
+    /// this panics!
+
+    pub fn mutate_add_node<R, G>(&mut self,
+                                 node_type: NT,
+                                 second_link_weight: Weight,
+                                 cache: &mut G,
+                                 rng: &mut R)
+                                 -> bool
+        where R: Rng,
+              G: GlobalCache
+    {
+        let link_innovation = match self.random_link_innovation(rng, |link| link.active) {
+            Some(li) => li,
+            None => return false,
+        };
+
+        // disable the original link gene, but keep it for structural compatibility.
+        let orig_link = {
+            let link = self.links.get_mut(&link_innovation).unwrap();
+            link.active = false;
+            *link
+        };
+
+        // reuse the node innovation of earlier splits of the same link, unless this genome
+        // already contains that node.
+        let mut new_node_innovation = cache.get_or_create_split_node_innovation(link_innovation);
+        if self.nodes.contains_key(&new_node_innovation) {
+            new_node_innovation = cache.create_node_innovation();
+        }
+        self.add_node(new_node_innovation, node_type);
+
+        let first_link = cache.get_or_create_link_innovation(orig_link.source_node,
+                                                             new_node_innovation);
+        self.add_link(orig_link.source_node,
+                      new_node_innovation,
+                      first_link,
+                      orig_link.weight);
+
+        let second_link = cache.get_or_create_link_innovation(new_node_innovation,
+                                                              orig_link.target_node);
+        self.add_link(new_node_innovation,
+                      orig_link.target_node,
+                      second_link,
+                      second_link_weight);
+
+        return true;
+    }
+
+    /// Uniformly modify the weight of link genes, each with a probability of `mutate_prob`. It is
+    /// guaranteed that this method makes a modification to at least one link (if it contains a
+    /// link!).
+    ///
+    /// Returns the number of modifications
+
+    pub fn mutate_link_weights_uniformly<R: Rng>(&mut self,
+                                                 mutate_prob: Prob,
+                                                 weight_perturbance: &WeightPerturbanceMethod,
+                                                 link_weight_range: &WeightRange,
+                                                 rng: &mut R)
+                                                 -> usize {
+        if self.links.is_empty() {
+            return 0;
+        }
+
+        let mut modifications = 0;
+
+        for link in self.links.values_mut() {
+            if mutate_prob.flip(rng) {
+                link.weight = weight_perturbance.perturb(link.weight, link_weight_range, rng);
+                modifications += 1;
+            }
+        }
+
+        if modifications == 0 {
+            // Make at least one change to a randomly selected link.
+            let li = self.random_link_innovation(rng, |_| true).unwrap();
+            let link = self.links.get_mut(&li).unwrap();
+            link.weight = weight_perturbance.perturb(link.weight, link_weight_range, rng);
+            modifications += 1;
+        }
+
+        return modifications;
+    }
+
+    /// Uniformly modify the parameters of node genes, each with a probability of `mutate_prob`.
+    /// Only nodes which accept incoming links are considered, as the parameters of all other
+    /// nodes have no effect. Like `mutate_link_weights_uniformly`, at least one node is modified
+    /// (if there is any such node).
+    ///
+    /// Returns the number of modifications
+
+    pub fn mutate_node_params_uniformly<R: Rng>(&mut self,
+                                                mutate_prob: Prob,
+                                                perturbance: &WeightPerturbanceMethod,
+                                                node_ranges: &NodeParamRanges,
+                                                rng: &mut R)
+                                                -> usize {
+        let candidates: Vec<NodeInnovation> = self.nodes
+                                                  .iter()
+                                                  .filter(|&(_, nt)| nt.accept_incoming_links())
+                                                  .map(|(&ni, _)| ni)
+                                                  .collect();
+
+        if candidates.is_empty() {
+            return 0;
+        }
+
+        let mut selected: Vec<NodeInnovation> = candidates.iter()
+                                                          .cloned()
+                                                          .filter(|_| mutate_prob.flip(rng))
+                                                          .collect();
+        if selected.is_empty() {
+            // Make at least one change to a randomly selected node.
+            selected.push(*rng.choose(&candidates).unwrap());
+        }
+
+        for ni in selected.iter() {
+            let params = self.node_params.get_mut(ni).unwrap();
+            params.bias = perturbance.perturb(params.bias, &node_ranges.bias, rng);
+            params.gain = perturbance.perturb(params.gain, &node_ranges.gain, rng);
+            params.time_constant = perturbance.perturb(params.time_constant,
+                                                       &node_ranges.time_constant,
+                                                       rng);
+        }
+
+        return selected.len();
+    }
+
+    /// Mutate the genome by removing a random link.
+    ///
+    /// Return `true` if the genome was modified. Otherwise `false`.
+
+    pub fn mutate_delete_link<R: Rng>(&mut self, rng: &mut R) -> bool {
+        match self.random_link_innovation(rng, |_| true) {
+            Some(li) => {
+                self.links.remove(&li);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<NT: ActivationNodeType> Genome<NT> {
+    /// Mutate the genome by removing a random hidden node together with all it's links.
+    ///
+    /// If `bridge_links` is `true`, each source of an active incoming link is connected to each
+    /// target of an active outgoing link of the removed node, unless such a link already exists.
+    /// The weight of a bridging link is the product of both weights, clipped to
+    /// `link_weight_range`. Self-loops of the removed node are not bridged.
+    ///
+    /// Return `true` if the genome was modified. Otherwise `false`.
+
+    pub fn mutate_delete_node<R, G>(&mut self,
+                                    bridge_links: bool,
+                                    link_weight_range: &WeightRange,
+                                    cache: &mut G,
+                                    rng: &mut R)
+                                    -> bool
+        where R: Rng,
+              G: GlobalCache
+    {
+        let hidden_nodes = self.hidden_nodes();
+
+        let removed_node = match rng.choose(&hidden_nodes) {
+            Some(&ni) => ni,
+            None => return false,
+        };
+
+        let mut incoming = Vec::new();
+        let mut outgoing = Vec::new();
+        self.visit_active_links(|source_node, target_node, weight| {
+            if source_node == target_node {
+                return;
+            }
+            if target_node == removed_node {
+                incoming.push((source_node, weight));
+            }
+            if source_node == removed_node {
+                outgoing.push((target_node, weight));
+            }
+        });
+
+        self.nodes.remove(&removed_node);
+        self.node_params.remove(&removed_node);
+        let removed_links: Vec<LinkInnovation> =
+            self.links
+                .iter()
+                .filter(|&(_, link)| {
+                    link.source_node == removed_node || link.target_node == removed_node
+                })
+                .map(|(&li, _)| li)
+                .collect();
+        for li in removed_links {
+            self.links.remove(&li);
+        }
+
+        if bridge_links {
+            for &(source_node, in_weight) in incoming.iter() {
+                for &(target_node, out_weight) in outgoing.iter() {
+                    if self.valid_link(source_node, target_node) {
+                        let weight = link_weight_range.clip_weight(Weight(in_weight.0 *
+                                                                          out_weight.0));
+                        let link_innovation =
+                            cache.get_or_create_link_innovation(source_node, target_node);
+                        self.add_link(source_node, target_node, link_innovation, weight);
+                    }
+                }
+            }
+        }
+
+        return true;
+    }
+
+    /// Returns all hidden nodes, i.e. nodes with role `NodeRole::Hidden`. In contrast to acyclic
+    /// genomes, output nodes usually accept outgoing links as well, so the links a node accepts
+    /// do not tell hidden nodes apart.
+
+    fn hidden_nodes(&self) -> Vec<NodeInnovation> {
+        self.nodes
+            .iter()
+            .filter(|&(_, nt)| nt.node_role() == NodeRole::Hidden)
+            .map(|(&ni, _)| ni)
+            .collect()
+    }
+}
+
+impl<NT: ActivationNodeType + PartialEq> Genome<NT> {
+    /// Mutate the genome by changing the type of a random hidden node to `node_type`. Input and
+    /// output nodes are never changed. Only hidden nodes of a different type are considered.
+    ///
+    /// Returns `false` if there is no hidden node of a different type, or if `node_type` does
+    /// not accept both incoming and outgoing links. Otherwise `true`.
+
+    pub fn mutate_change_node_type<R: Rng>(&mut self, node_type: NT, rng: &mut R) -> bool {
+        if !(node_type.accept_incoming_links() && node_type.accept_outgoing_links()) {
+            return false;
+        }
+
+        let candidates: Vec<NodeInnovation> = self.hidden_nodes()
+                                                  .into_iter()
+                                                  .filter(|ni| self.nodes[ni] != node_type)
+                                                  .collect();
+
+        match rng.choose(&candidates).cloned() {
+            Some(ni) => {
+                self.nodes.insert(ni, node_type);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<NT: NodeType + PartialEq> Genome<NT> {
+    /// Counts the matching node innovations of `left_genome` and `right_genome` which differ in
+    /// their node type.
+
+    pub fn node_type_mismatches(left_genome: &Self, right_genome: &Self) -> usize {
+        let mut mismatches = 0;
+
+        align_sorted_iterators(left_genome.nodes.iter(),
+                               right_genome.nodes.iter(),
+                               |&(kl, _), &(kr, _)| Ord::cmp(kl, kr),
+                               |alignment| {
+                                   if let Alignment::Match((_, left_type), (_, right_type)) =
+                                          alignment {
+                                       if left_type != right_type {
+                                           mismatches += 1;
+                                       }
+                                   }
+                               });
+
+        mismatches
+    }
+}
+
+impl<NT: NodeType + PartialEq> Distance<Genome<NT>> for GenomeDistance {
+    fn distance(&self, genome_left: &Genome<NT>, genome_right: &Genome<NT>) -> f64 {
+        let link_metric = Genome::link_alignment_metric(genome_left, genome_right);
+        let node_metric = Genome::node_alignment_metric(genome_left, genome_right);
+        let node_type_mismatches = Genome::node_type_mismatches(genome_left, genome_right);
+
+        self.link_distance(&link_metric) + self.node_distance(&node_metric, node_type_mismatches)
+    }
+}
+
+/// Written as
+///
+/// ```text
+/// (recurrent-genome
+///   (nodes (node innovation node-type bias gain time-constant)...)
+///   (links (link innovation source target weight active)...))
+/// ```
+
+impl<NT: NodeType + Persist> Persist for Genome<NT> {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "(recurrent-genome\n  (nodes"));
+        for (node_innovation, node_type) in self.nodes.iter() {
+            let params = &self.node_params[node_innovation];
+            try!(write!(wr, "\n    (node "));
+            try!(node_innovation.persist(wr));
+            try!(node_type.persist(wr));
+            try!(params.bias.persist(wr));
+            try!(params.gain.persist(wr));
+            try!(params.time_constant.persist(wr));
+            try!(write!(wr, ")"));
+        }
+        try!(write!(wr, ")\n  (links"));
+        for (link_innovation, link) in self.links.iter() {
+            try!(write!(wr, "\n    (link "));
+            try!(link_innovation.persist(wr));
+            try!(link.source_node.persist(wr));
+            try!(link.target_node.persist(wr));
+            try!(link.weight.persist(wr));
+            try!(link.active.persist(wr));
+            try!(write!(wr, ")"));
+        }
+        write!(wr, "))\n")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("recurrent-genome"));
+        let mut genome = Genome::new();
+
+        try!(rd.open_keyword("nodes"));
+        while !rd.at_close() {
+            try!(rd.open_keyword("node"));
+            let node_innovation = try!(NodeInnovation::restore(rd));
+            let node_type = try!(NT::restore(rd));
+            let node_params = NodeParams {
+                bias: try!(Weight::restore(rd)),
+                gain: try!(Weight::restore(rd)),
+                time_constant: try!(Weight::restore(rd)),
+            };
+            try!(rd.close());
+
+            if genome.has_node(node_innovation) {
+                return Err(PersistError::Invalid(format!("duplicate node {:?}", node_innovation)));
+            }
+            genome.add_node_with_params(node_innovation, node_type, node_params);
+        }
+        try!(rd.close());
+
+        try!(rd.open_keyword("links"));
+        while !rd.at_close() {
+            try!(rd.open_keyword("link"));
+            let link_innovation = try!(LinkInnovation::restore(rd));
+            let source_node = try!(NodeInnovation::restore(rd));
+            let target_node = try!(NodeInnovation::restore(rd));
+            let weight = try!(Weight::restore(rd));
+            let active: bool = try!(rd.atom());
+            try!(rd.close());
+
+            if genome.links.contains_key(&link_innovation) ||
+               !genome.valid_link(source_node, target_node) {
+                return Err(PersistError::Invalid(format!("link {:?} from {:?} to {:?}",
+                                                         link_innovation,
+                                                         source_node,
+                                                         target_node)));
+            }
+            genome.add_link_with_active(source_node, target_node, link_innovation, weight, active);
+        }
+        try!(rd.close());
+
+        try!(rd.close());
+        Ok(genome)
+    }
+}
+
+/// Implementation for mating.
+
+pub struct Mater<'a, N, S, C>
+    where N: NodeType + 'a,
+          S: ElementStrategy<N> + 'a,
+          C: GlobalCache + 'a
+{
+    // probability for crossover. P_mutate = 1.0 - p_crossover
+    pub p_crossover: Prob,
+    pub p_crossover_detail: ProbabilisticCrossover,
+    pub p_mutate_element: Prob,
+    pub weight_perturbance: WeightPerturbanceMethod,
+    pub mutate_weights: MutateMethodWeighting,
+    pub mutation_schedule: MutationSchedule,
+    pub bridge_deleted_nodes: bool,
+    // usually a `&mut GlobalInnovationCache`, or a `&ConcurrentInnovationCache` to share the
+    // innovations between `Mater`s on different threads.
+    pub global_cache: C,
+    pub element_strategy: &'a S,
+    pub _n: PhantomData<N>,
+}
+
+/// Clones the settings. Both `Mater`s share the same `element_strategy`. Whether they share the
+/// same innovation cache depends on how `C` is cloned.
+
+impl<'a, N, S, C> Clone for Mater<'a, N, S, C>
+    where N: NodeType + 'a,
+          S: ElementStrategy<N> + 'a,
+          C: GlobalCache + Clone + 'a
+{
+    fn clone(&self) -> Self {
+        self.with_global_cache(self.global_cache.clone())
+    }
+}
+
+impl<'a, N, S, C> Mater<'a, N, S, C>
+    where N: NodeType + 'a,
+          S: ElementStrategy<N> + 'a,
+          C: GlobalCache + 'a
+{
+    /// Returns a `Mater` with the same settings as `self`, using `global_cache`.
+
+    pub fn with_global_cache<D>(&self, global_cache: D) -> Mater<'a, N, S, D>
+        where D: GlobalCache + 'a
+    {
+        Mater {
+            p_crossover: self.p_crossover,
+            p_crossover_detail: self.p_crossover_detail,
+            p_mutate_element: self.p_mutate_element,
+            weight_perturbance: self.weight_perturbance,
+            mutate_weights: self.mutate_weights,
+            mutation_schedule: self.mutation_schedule,
+            bridge_deleted_nodes: self.bridge_deleted_nodes,
+            global_cache: global_cache,
+            element_strategy: self.element_strategy,
+            _n: PhantomData,
+        }
+    }
+}
+
+impl<'a, N, S, C> GenomeMater<Genome<N>> for Mater<'a, N, S, C>
+    where N: ActivationNodeType + PartialEq + 'a,
+          S: ElementStrategy<N> + 'a,
+          C: GlobalCache + 'a
+{
+    fn p_crossover(&self) -> Prob {
+        self.p_crossover
+    }
+
+    fn p_crossover_detail(&self) -> ProbabilisticCrossover {
+        self.p_crossover_detail
+    }
+
+    fn mutate_weights(&self) -> MutateMethodWeighting {
+        self.mutate_weights
+    }
+
+    fn mutation_schedule(&self) -> MutationSchedule {
+        self.mutation_schedule
+    }
+
+    fn mutate<R: Rng>(&mut self,
+                      offspring: &mut Genome<N>,
+                      mutate_method: MutateMethod,
+                      rng: &mut R)
+                      -> bool {
+        match mutate_method {
+            MutateMethod::ModifyWeight => {
+                let modifications =
+                    offspring.mutate_link_weights_uniformly(self.p_mutate_element,
+                                                            &self.weight_perturbance,
+                                                            &self.element_strategy
+                                                                 .link_weight_range(),
+                                                            rng);
+
+                modifications > 0
+            }
+            MutateMethod::AddConnection => {
+                let link_weight = self.element_strategy
+                                      .link_weight_range()
+                                      .random_weight(rng);
+                offspring.mutate_add_link(link_weight, &mut self.global_cache, rng)
+            }
+            MutateMethod::EnableConnection => offspring.mutate_enable_link(rng),
+            MutateMethod::DeleteConnection => offspring.mutate_delete_link(rng),
+            MutateMethod::ChangeNodeType => {
+                let node_type = self.element_strategy.random_node_type(rng);
+                offspring.mutate_change_node_type(node_type, rng)
+            }
+            MutateMethod::DeleteNode => {
+                offspring.mutate_delete_node(self.bridge_deleted_nodes,
+                                             &self.element_strategy.link_weight_range(),
+                                             &mut self.global_cache,
+                                             rng)
+            }
+            MutateMethod::AddNode => {
+                let second_link_weight = self.element_strategy.full_link_weight();
+                let node_type = self.element_strategy.random_node_type(rng);
+                offspring.mutate_add_node(node_type, second_link_weight, &mut self.global_cache, rng)
+            }
+            MutateMethod::ModifyNodeParams => {
+                let node_ranges = NodeParamRanges::from_strategy::<N, S>(self.element_strategy);
+                let modifications =
+                    offspring.mutate_node_params_uniformly(self.p_mutate_element,
+                                                           &self.weight_perturbance,
+                                                           &node_ranges,
+                                                           rng);
+
+                modifications > 0
+            }
+        }
+    }
+
+    fn crossover<R: Rng>(&mut self,
+                         parent_left: &Genome<N>,
+                         parent_right: &Genome<N>,
+                         crossover: &ProbabilisticCrossover,
+                         rng: &mut R)
+                         -> Genome<N> {
+        Genome::crossover(parent_left,
+                          parent_right,
+                          crossover,
+                          &self.element_strategy.link_weight_range(),
+                          &NodeParamRanges::from_strategy::<N, S>(self.element_strategy),
+                          rng)
+    }
+}
+
+impl<'a, N, S, C> Mate<Genome<N>> for Mater<'a, N, S, C>
+    where N: ActivationNodeType + PartialEq + 'a,
+          S: ElementStrategy<N> + 'a,
+          C: GlobalCache + 'a
+{
+    fn mate<R: Rng>(&mut self,
+                    parent_left: &Genome<N>,
+                    parent_right: &Genome<N>,
+                    fitness_left: Fitness,
+                    fitness_right: Fitness,
+                    prefer_mutate: bool,
+                    rng: &mut R)
+                    -> Genome<N> {
+        self.mate_traced(parent_left,
+                         parent_right,
+                         fitness_left,
+                         fitness_right,
+                         prefer_mutate,
+                         &mut Vec::new(),
+                         rng)
+            .0
+    }
+
+    fn mate_traced<R: Rng>(&mut self,
+                           parent_left: &Genome<N>,
+                           parent_right: &Genome<N>,
+                           fitness_left: Fitness,
+                           fitness_right: Fitness,
+                           prefer_mutate: bool,
+                           operators: &mut Vec<Operator>,
+                           rng: &mut R)
+                           -> (Genome<N>, Contributors) {
+        self.mate_with(parent_left,
+                       parent_right,
+                       fitness_left,
+                       fitness_right,
+                       prefer_mutate,
+                       operators,
+                       rng)
+    }
+}
+
+/// Stepwise evaluation of a recurrent genome.
+///
+/// Each call to `step` propagates the node activations of the previous time step along all
+/// active links, i.e. a signal travels one link per time step. Each node then moves towards
+/// it's new activation as determined by it's `NodeParams`. The activations are kept between
+/// calls to `step` until `reset` is called.
+
+#[derive(Clone, Debug)]
+pub struct RecurrentNetwork<NT: ActivationNodeType> {
+    node_types: Vec<NT>,
+    node_params: Vec<NodeParams>,
+    input_nodes: Vec<usize>,
+    output_nodes: Vec<usize>,
+    // (source, target, weight) of each active link.
+    links: Vec<(usize, usize, f64)>,
+    state: Vec<f64>,
+    next_state: Vec<f64>,
+}
+
+impl<NT: ActivationNodeType> RecurrentNetwork<NT> {
+    /// Builds the network of `genome`. Inputs and outputs are ordered by node innovation.
+
+    pub fn from_genome(genome: &Genome<NT>) -> Self {
+        let mut index_of = BTreeMap::new();
+        let mut node_types = Vec::new();
+        let mut node_params = Vec::new();
+        let mut input_nodes = Vec::new();
+        let mut output_nodes = Vec::new();
+
+        for (i, (&node_innovation, node_type)) in genome.nodes.iter().enumerate() {
+            index_of.insert(node_innovation, i);
+            match node_type.node_role() {
+                NodeRole::Input => input_nodes.push(i),
+                NodeRole::Output => output_nodes.push(i),
+                NodeRole::Bias | NodeRole::Hidden => {}
+            }
+            node_types.push(node_type.clone());
+            node_params.push(genome.node_params[&node_innovation]);
+        }
+
+        let mut links = Vec::new();
+        genome.visit_active_links(|source_node, target_node, weight| {
+            links.push((index_of[&source_node], index_of[&target_node], weight.0));
+        });
+
+        let n = node_types.len();
+        RecurrentNetwork {
+            node_types: node_types,
+            node_params: node_params,
+            input_nodes: input_nodes,
+            output_nodes: output_nodes,
+            links: links,
+            state: vec![0.0; n],
+            next_state: vec![0.0; n],
+        }
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.input_nodes.len()
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.output_nodes.len()
+    }
+
+    /// The current activation of all nodes, ordered by node innovation.
+
+    pub fn state(&self) -> &[f64] {
+        &self.state
+    }
+
+    /// Clears the activation of all nodes.
+
+    pub fn reset(&mut self) {
+        for s in self.state.iter_mut() {
+            *s = 0.0;
+        }
+    }
+
+    /// Advances the network by one time step, feeding in `inputs` and returning the
+    /// activation of the output nodes.
+    ///
+    /// # Panics
+    ///
+    /// If the length of `inputs` does not match the number of input nodes.
+
+    pub fn step(&mut self, inputs: &[f64]) -> Vec<f64> {
+        assert!(inputs.len() == self.input_nodes.len());
+
+        for (&node, &input) in self.input_nodes.iter().zip(inputs.iter()) {
+            self.state[node] = input;
+        }
+
+        for s in self.next_state.iter_mut() {
+            *s = 0.0;
+        }
+        for &(source, target, weight) in self.links.iter() {
+            self.next_state[target] += weight * self.state[source];
+        }
+
+        for (i, (node_type, params)) in self.node_types
+                                             .iter()
+                                             .zip(self.node_params.iter())
+                                             .enumerate() {
+            self.next_state[i] = match node_type.node_role() {
+                NodeRole::Input => self.state[i],
+                NodeRole::Bias => 1.0,
+                NodeRole::Hidden | NodeRole::Output => {
+                    let target = node_type.activate(params.gain.0 * self.next_state[i] +
+                                                    params.bias.0);
+                    self.state[i] + (target - self.state[i]) / params.time_constant.0
+                }
+            };
+        }
+
+        ::std::mem::swap(&mut self.state, &mut self.next_state);
+
+        self.output_nodes.iter().map(|&node| self.state[node]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Genome, RecurrentNetwork, GlobalCache, NodeParams, NodeParamRanges};
+    use genomes::{ActivationNodeType, NodeRole};
+    use genomes::acyclic_network::{NodeType, GlobalInnovationCache};
+    use crossover::{ProbabilisticCrossover, BlendMethod};
+    use prob::Prob;
+    use weight::{Weight, WeightRange, WeightPerturbanceMethod};
+    use persist::{Persist, Reader, PersistError, save, load};
+    use rand::{self, XorShiftRng, SeedableRng};
+    use std::io::{self, Write};
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum NT {
+        Input,
+        Hidden,
+        Tanh,
+        Output,
+    }
+
+    impl NodeType for NT {
+        fn accept_incoming_links(&self) -> bool {
+            *self != NT::Input
+        }
+        fn accept_outgoing_links(&self) -> bool {
+            true
+        }
+    }
+
+    impl ActivationNodeType for NT {
+        fn node_role(&self) -> NodeRole {
+            match *self {
+                NT::Input => NodeRole::Input,
+                NT::Hidden | NT::Tanh => NodeRole::Hidden,
+                NT::Output => NodeRole::Output,
+            }
+        }
+        fn activate(&self, input: f64) -> f64 {
+            match *self {
+                NT::Tanh => input.tanh(),
+                _ => input,
+            }
+        }
+    }
+
+    impl Persist for NT {
+        fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+            write!(wr, "{:?} ", self)
+        }
+
+        fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+            match try!(rd.next()) {
+                "Input" => Ok(NT::Input),
+                "Hidden" => Ok(NT::Hidden),
+                "Tanh" => Ok(NT::Tanh),
+                "Output" => Ok(NT::Output),
+                token => Err(PersistError::Invalid(token.to_string())),
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate node_innovation")]
+    fn test_add_duplicate_node() {
+        let mut cache = GlobalInnovationCache::new();
+        let n0 = cache.create_node_innovation();
+        let mut genome = Genome::<NT>::new();
+        genome.add_node(n0, NT::Hidden);
+        genome.add_node(n0, NT::Hidden);
+    }
+
+    #[test]
+    fn test_cycles_allowed() {
+        let mut cache = GlobalInnovationCache::new();
+        let n0 = cache.create_node_innovation();
+        let n1 = cache.create_node_innovation();
+        let n2 = cache.create_node_innovation();
+
+        let mut genome = Genome::<NT>::new();
+        genome.add_node(n0, NT::Input);
+        genome.add_node(n1, NT::Hidden);
+        genome.add_node(n2, NT::Hidden);
+
+        assert!(genome.valid_link(n1, n1));
+        genome.add_link(n1, n1, cache.get_or_create_link_innovation(n1, n1), Weight(1.0));
+        genome.add_link(n1, n2, cache.get_or_create_link_innovation(n1, n2), Weight(1.0));
+        assert!(genome.valid_link(n2, n1));
+        genome.add_link(n2, n1, cache.get_or_create_link_innovation(n2, n1), Weight(1.0));
+        assert_eq!(3, genome.link_count());
+
+        // no duplicate links and no links into input nodes.
+        assert!(!genome.valid_link(n1, n2));
+        assert!(!genome.valid_link(n1, n0));
+    }
+
+    #[test]
+    fn test_mutate_add_link_until_full() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let mut genome = Genome::<NT>::new();
+        genome.add_node(cache.create_node_innovation(), NT::Input);
+        genome.add_node(cache.create_node_innovation(), NT::Output);
+
+        // input -> output, output -> output. links into the input are not allowed.
+        assert!(genome.mutate_add_link(Weight(0.5), &mut cache, &mut rng));
+        assert!(genome.mutate_add_link(Weight(0.5), &mut cache, &mut rng));
+        assert!(!genome.mutate_add_link(Weight(0.5), &mut cache, &mut rng));
+        assert_eq!(2, genome.link_count());
+    }
+
+    #[test]
+    fn test_mutate_add_link_reenables_disabled_link() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let mut genome = Genome::<NT>::new();
+        let n0 = cache.create_node_innovation();
+        let n1 = cache.create_node_innovation();
+        genome.add_node(n0, NT::Input);
+        genome.add_node(n1, NT::Output);
+        let l0 = cache.get_or_create_link_innovation(n0, n1);
+        let l1 = cache.get_or_create_link_innovation(n1, n1);
+        genome.add_link_with_active(n0, n1, l0, Weight(0.25), false);
+        genome.add_link_with_active(n1, n1, l1, Weight(0.25), false);
+
+        assert!(genome.mutate_add_link(Weight(0.5), &mut cache, &mut rng));
+        assert!(genome.mutate_add_link(Weight(0.5), &mut cache, &mut rng));
+        assert!(!genome.mutate_add_link(Weight(0.5), &mut cache, &mut rng));
+        assert_eq!(2, genome.link_count());
+        assert!(genome.link(l0).unwrap().is_active());
+        assert!(genome.link(l1).unwrap().is_active());
+        assert_eq!(0.25, genome.link(l0).unwrap().weight().0);
+    }
+
+    #[test]
+    fn test_mutate_add_node_shares_split_innovation() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let mut genome = Genome::<NT>::new();
+        let n0 = cache.create_node_innovation();
+        let n1 = cache.create_node_innovation();
+        genome.add_node(n0, NT::Input);
+        genome.add_node(n1, NT::Output);
+        genome.add_link(n0, n1, cache.get_or_create_link_innovation(n0, n1), Weight(0.5));
+
+        let mut left = genome.clone();
+        let mut right = genome.clone();
+        assert!(left.mutate_add_node(NT::Hidden, Weight(1.0), &mut cache, &mut rng));
+        assert!(right.mutate_add_node(NT::Hidden, Weight(1.0), &mut cache, &mut rng));
+
+        let mut hidden = None;
+        left.visit_nodes(|ni, _| {
+            if ni != n0 && ni != n1 {
+                hidden = Some(ni);
+            }
+        });
+        let hidden = hidden.unwrap();
+        assert!(right.has_node(hidden));
+        assert_eq!(3, right.node_count());
+
+        // a genome which already contains the split node gets a fresh one.
+        let mut other = genome.clone();
+        other.add_node(hidden, NT::Hidden);
+        assert!(other.mutate_add_node(NT::Hidden, Weight(1.0), &mut cache, &mut rng));
+        assert_eq!(4, other.node_count());
+    }
+
+    #[test]
+    fn test_mutate_delete_node_keeps_outputs() {
+        let mut cache = GlobalInnovationCache::new();
+        let i = cache.create_node_innovation();
+        let h = cache.create_node_innovation();
+        let o = cache.create_node_innovation();
+
+        // the output node has a back-edge, so it accepts both incoming and outgoing links.
+        let mut genome = Genome::<NT>::new();
+        genome.add_node(i, NT::Input);
+        genome.add_node(h, NT::Hidden);
+        genome.add_node(o, NT::Output);
+        genome.add_link(i, h, cache.get_or_create_link_innovation(i, h), Weight(1.0));
+        genome.add_link(h, o, cache.get_or_create_link_innovation(h, o), Weight(1.0));
+        genome.add_link(o, h, cache.get_or_create_link_innovation(o, h), Weight(1.0));
+
+        for seed in 1..20 {
+            let mut rng = XorShiftRng::from_seed([seed, 2, 3, 4]);
+            let mut mutated = genome.clone();
+            assert!(mutated.mutate_delete_node(false,
+                                               &WeightRange::bipolar(1.0),
+                                               &mut cache,
+                                               &mut rng));
+            assert!(!mutated.has_node(h));
+            assert!(mutated.has_node(i) && mutated.has_node(o));
+            assert_eq!(0, mutated.link_count());
+
+            // only input and output nodes are left.
+            assert!(!mutated.mutate_delete_node(false,
+                                                &WeightRange::bipolar(1.0),
+                                                &mut cache,
+                                                &mut rng));
+            assert_eq!(2, mutated.node_count());
+        }
+    }
+
+    #[test]
+    fn test_mutate_change_node_type() {
+        let mut cache = GlobalInnovationCache::new();
+        let i = cache.create_node_innovation();
+        let h = cache.create_node_innovation();
+        let o = cache.create_node_innovation();
+
+        // the output node has a back-edge, so it accepts both incoming and outgoing links.
+        let mut genome = Genome::<NT>::new();
+        genome.add_node(i, NT::Input);
+        genome.add_node(h, NT::Hidden);
+        genome.add_node(o, NT::Output);
+        genome.add_link(i, h, cache.get_or_create_link_innovation(i, h), Weight(1.0));
+        genome.add_link(h, o, cache.get_or_create_link_innovation(h, o), Weight(1.0));
+        genome.add_link(o, h, cache.get_or_create_link_innovation(o, h), Weight(1.0));
+
+        for seed in 1..20 {
+            let mut rng = XorShiftRng::from_seed([seed, 2, 3, 4]);
+            let mut mutated = genome.clone();
+
+            // the only hidden node already has this type.
+            assert!(!mutated.mutate_change_node_type(NT::Hidden, &mut rng));
+            assert_eq!(0, Genome::node_type_mismatches(&genome, &mutated));
+
+            assert!(mutated.mutate_change_node_type(NT::Tanh, &mut rng));
+            assert_eq!(1, Genome::node_type_mismatches(&genome, &mutated));
+            assert_eq!(NT::Tanh, mutated.nodes[&h]);
+            assert_eq!(NT::Output, mutated.nodes[&o]);
+
+            assert!(!mutated.mutate_change_node_type(NT::Tanh, &mut rng));
+        }
+    }
+
+    #[test]
+    fn test_crossover_identical() {
+        let mut rng = rand::thread_rng();
+        let mut cache = GlobalInnovationCache::new();
+        let mut genome = Genome::<NT>::new();
+        let n0 = cache.create_node_innovation();
+        let n1 = cache.create_node_innovation();
+        genome.add_node(n0, NT::Input);
+        genome.add_node(n1, NT::Output);
+        genome.add_link(n0, n1, cache.get_or_create_link_innovation(n0, n1), Weight(0.5));
+        genome.add_link(n1, n1, cache.get_or_create_link_innovation(n1, n1), Weight(0.25));
+
+        let c = ProbabilisticCrossover {
+            prob_match_left: Prob::new(0.5),
+            prob_disjoint_left: Prob::new(1.0),
+            prob_excess_left: Prob::new(1.0),
+            prob_disjoint_right: Prob::new(0.0),
+            prob_excess_right: Prob::new(0.0),
+            blend: BlendMethod::Select,
+        };
+
+        let offspring = Genome::crossover(&genome,
+                                          &genome,
+                                          &c,
+                                          &WeightRange::bipolar(1.0),
+                                          &node_ranges(),
+                                          &mut rng);
+        assert_eq!(2, offspring.node_count());
+        assert_eq!(2, offspring.link_count());
+
+        let m = Genome::link_alignment_metric(&genome, &offspring);
+        assert_eq!(2, m.matching);
+        assert_eq!(0.0, m.weight_distance);
+    }
+
+    fn node_ranges() -> NodeParamRanges {
+        NodeParamRanges {
+            bias: WeightRange::bipolar(1.0),
+            gain: WeightRange::new(2.0, 0.0),
+            time_constant: WeightRange::new(10.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn test_node_params() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut cache = GlobalInnovationCache::new();
+        let n0 = cache.create_node_innovation();
+        let n1 = cache.create_node_innovation();
+        let mut genome = Genome::<NT>::new();
+        genome.add_node(n0, NT::Input);
+        genome.add_node(n1, NT::Output);
+
+        let ranges = node_ranges();
+        let mut mutated = genome.clone();
+        assert_eq!(1,
+                   mutated.mutate_node_params_uniformly(Prob::new(0.0),
+                                                        &WeightPerturbanceMethod::Random,
+                                                        &ranges,
+                                                        &mut rng));
+
+        // the parameters of the input node have no effect and are left alone.
+        assert_eq!(0.0, mutated.node_params(n0).unwrap().distance(&NodeParams::new()));
+        let params = mutated.node_params(n1).unwrap();
+        assert!(params.distance(&NodeParams::new()) > 0.0);
+        assert!(ranges.bias.in_range(params.bias));
+        assert!(ranges.gain.in_range(params.gain));
+        assert!(ranges.time_constant.in_range(params.time_constant));
+
+        let m = Genome::node_alignment_metric(&genome, &mutated);
+        assert_eq!(2, m.matching);
+        assert_eq!(params.distance(&NodeParams::new()), m.weight_distance);
+
+        // crossover blends matching nodes within the ranges.
+        let c = ProbabilisticCrossover {
+            prob_match_left: Prob::new(0.5),
+            prob_disjoint_left: Prob::new(1.0),
+            prob_excess_left: Prob::new(1.0),
+            prob_disjoint_right: Prob::new(0.0),
+            prob_excess_right: Prob::new(0.0),
+            blend: BlendMethod::Average,
+        };
+        let offspring = Genome::crossover(&genome,
+                                          &mutated,
+                                          &c,
+                                          &WeightRange::bipolar(1.0),
+                                          &ranges,
+                                          &mut rng);
+        let blended = offspring.node_params(n1).unwrap();
+        assert!((blended.gain.0 - 0.5 * (1.0 + params.gain.0)).abs() < 1e-9);
+        assert!((blended.time_constant.0 - 0.5 * (1.0 + params.time_constant.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_step_node_params() {
+        let mut cache = GlobalInnovationCache::new();
+        let n0 = cache.create_node_innovation();
+        let n1 = cache.create_node_innovation();
+
+        let mut genome = Genome::<NT>::new();
+        genome.add_node(n0, NT::Input);
+        genome.add_node_with_params(n1,
+                                    NT::Output,
+                                    NodeParams {
+                                        bias: Weight(1.0),
+                                        gain: Weight(2.0),
+                                        time_constant: Weight(2.0),
+                                    });
+        genome.add_link(n0, n1, cache.get_or_create_link_innovation(n0, n1), Weight(1.0));
+
+        // the output moves halfway towards 2.0 * input + 1.0 in each step.
+        let mut net = RecurrentNetwork::from_genome(&genome);
+        assert_eq!(vec![0.5], net.step(&[0.0]));
+        assert_eq!(vec![0.75], net.step(&[0.0]));
+        assert_eq!(vec![1.875], net.step(&[1.0]));
+    }
+
+    #[test]
+    fn test_step_keeps_state() {
+        let mut cache = GlobalInnovationCache::new();
+        let n0 = cache.create_node_innovation();
+        let n1 = cache.create_node_innovation();
+        let n2 = cache.create_node_innovation();
+
+        // input -> hidden (with self-loop) -> output. The hidden node integrates the input.
+        let mut genome = Genome::<NT>::new();
+        genome.add_node(n0, NT::Input);
+        genome.add_node(n1, NT::Hidden);
+        genome.add_node(n2, NT::Output);
+        genome.add_link(n0, n1, cache.get_or_create_link_innovation(n0, n1), Weight(1.0));
+        genome.add_link(n1, n1, cache.get_or_create_link_innovation(n1, n1), Weight(1.0));
+        genome.add_link(n1, n2, cache.get_or_create_link_innovation(n1, n2), Weight(1.0));
+
+        let mut net = RecurrentNetwork::from_genome(&genome);
+        assert_eq!(1, net.num_inputs());
+        assert_eq!(1, net.num_outputs());
+
+        assert_eq!(vec![0.0], net.step(&[1.0]));
+        assert_eq!(vec![1.0], net.step(&[1.0]));
+        assert_eq!(vec![2.0], net.step(&[1.0]));
+        assert_eq!(vec![3.0], net.step(&[0.0]));
+
+        net.reset();
+        assert_eq!(vec![0.0], net.step(&[0.0]));
+    }
+
+    #[test]
+    fn test_persist() {
+        let mut cache = GlobalInnovationCache::new();
+        let n0 = cache.create_node_innovation();
+        let n1 = cache.create_node_innovation();
+
+        let mut genome = Genome::<NT>::new();
+        genome.add_node(n0, NT::Input);
+        genome.add_node(n1, NT::Output);
+        genome.add_link(n0, n1, cache.get_or_create_link_innovation(n0, n1), Weight(0.25));
+        genome.add_link_with_active(n1,
+                                    n1,
+                                    cache.get_or_create_link_innovation(n1, n1),
+                                    Weight(-1.5),
+                                    false);
+
+        let mut buf = Vec::new();
+        save(&genome, &mut buf).unwrap();
+        let archive = String::from_utf8(buf).unwrap();
+        let restored: Genome<NT> = load(&archive).unwrap();
+        assert_eq!(format!("{:?}", genome), format!("{:?}", restored));
+
+        // links into input nodes are rejected
+        let invalid = archive.replace("(link 1 1 1 -1.5 false )", "(link 1 1 0 -1.5 false )");
+        assert!(invalid != archive);
+        assert!(load::<Genome<NT>>(&invalid).is_err());
+    }
+}
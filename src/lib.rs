@@ -15,6 +15,9 @@ pub mod alignment_metric;
 pub mod mutate;
 pub mod fitness;
 pub mod population;
+pub mod lineage;
+pub mod seed;
+pub mod persist;
 pub mod prob;
 pub mod crossover;
 pub mod genomes;
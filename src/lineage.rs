@@ -0,0 +1,478 @@
+use mutate::MutateMethod;
+use fitness::Fitness;
+use persist::{Persist, Reader, PersistError};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+/// Uniquely identifies an individual within a `Genealogy`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IndividualId(pub usize);
+
+/// The operator that produced (part of) an individual.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// Part of the initial population.
+    Initial,
+    /// Copied as-is from the previous generation.
+    EliteCopy,
+    Crossover,
+    Mutate(MutateMethod),
+}
+
+/// The parents which contributed genes to an offspring, as reported by `Mate::mate_traced`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Contributors {
+    /// Only `parent_left`, e.g. the offspring is a mutated copy of it.
+    Left,
+    /// Only `parent_right`.
+    Right,
+    Both,
+}
+
+/// Where an individual came from.
+///
+/// The `id` is `None` until the individual is registered with a `Genealogy`. Parents which
+/// were never registered are not listed in `parents`.
+
+#[derive(Debug, Clone)]
+pub struct Lineage {
+    pub id: Option<IndividualId>,
+    pub parents: Vec<IndividualId>,
+    pub generation: usize,
+    /// The operators in the order they were applied.
+    pub operators: Vec<Operator>,
+}
+
+impl Lineage {
+    pub fn initial() -> Lineage {
+        Lineage {
+            id: None,
+            parents: Vec::new(),
+            generation: 0,
+            operators: vec![Operator::Initial],
+        }
+    }
+
+    /// The lineage of an offspring of `parents`, produced by `operators`.
+
+    pub fn offspring(parents: &[&Lineage], operators: Vec<Operator>) -> Lineage {
+        let mut parent_ids: Vec<IndividualId> = Vec::new();
+        for parent in parents {
+            if let Some(id) = parent.id {
+                if !parent_ids.contains(&id) {
+                    parent_ids.push(id);
+                }
+            }
+        }
+        Lineage {
+            id: None,
+            parents: parent_ids,
+            generation: 0,
+            operators: operators,
+        }
+    }
+
+    /// The lineage of an elite copy of an individual with lineage `self`.
+
+    pub fn elite_copy(&self) -> Lineage {
+        Lineage::offspring(&[self], vec![Operator::EliteCopy])
+    }
+}
+
+/// A registered individual.
+
+#[derive(Debug, Clone)]
+pub struct GenealogyRecord {
+    pub lineage: Lineage,
+    pub fitness: Option<Fitness>,
+}
+
+/// Records the lineage of every individual ever registered, so that the ancestry of an
+/// individual can be reconstructed after it's ancestors died out.
+
+#[derive(Debug)]
+pub struct Genealogy {
+    next_id: usize,
+    records: BTreeMap<IndividualId, GenealogyRecord>,
+}
+
+impl Genealogy {
+    pub fn new() -> Genealogy {
+        Genealogy {
+            next_id: 0,
+            records: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn get(&self, id: IndividualId) -> Option<&GenealogyRecord> {
+        self.records.get(&id)
+    }
+
+    /// Assigns a fresh id to `lineage` and records it as born in `generation`. Does nothing if
+    /// `lineage` is already registered.
+
+    pub fn register(&mut self,
+                    lineage: &mut Lineage,
+                    generation: usize,
+                    fitness: Option<Fitness>)
+                    -> IndividualId {
+        if let Some(id) = lineage.id {
+            return id;
+        }
+
+        let id = IndividualId(self.next_id);
+        self.next_id += 1;
+
+        lineage.id = Some(id);
+        lineage.generation = generation;
+        self.records.insert(id,
+                            GenealogyRecord {
+                                lineage: lineage.clone(),
+                                fitness: fitness,
+                            });
+        id
+    }
+
+    /// Returns `id` and all of it's registered ancestors, sorted by id. Because parents are
+    /// always registered before their offspring, parents precede their offspring.
+
+    pub fn ancestry(&self, id: IndividualId) -> Vec<&GenealogyRecord> {
+        self.ancestor_ids(vec![id]).iter().map(|id| &self.records[id]).collect()
+    }
+
+    /// Removes all records which are neither one of the `live` individuals nor an ancestor of
+    /// them, e.g. those of lineages which died out. Returns the number of removed records.
+    ///
+    /// Without pruning, the genealogy grows with every generation.
+
+    pub fn prune<I>(&mut self, live: I) -> usize
+        where I: IntoIterator<Item = IndividualId>
+    {
+        let keep = self.ancestor_ids(live.into_iter().collect());
+        let dead: Vec<IndividualId> = self.records
+                                          .keys()
+                                          .cloned()
+                                          .filter(|id| !keep.contains(id))
+                                          .collect();
+        for id in dead.iter() {
+            self.records.remove(id);
+        }
+        dead.len()
+    }
+
+    /// Returns the registered `ids` and all of their registered ancestors.
+
+    fn ancestor_ids(&self, ids: Vec<IndividualId>) -> BTreeSet<IndividualId> {
+        let mut seen = BTreeSet::new();
+        let mut stack = ids;
+
+        while let Some(id) = stack.pop() {
+            if seen.contains(&id) {
+                continue;
+            }
+            if let Some(record) = self.records.get(&id) {
+                seen.insert(id);
+                stack.extend(record.lineage.parents.iter().cloned());
+            }
+        }
+
+        seen
+    }
+
+    /// Writes the ancestry DAG of `id` in Graphviz DOT format. Edges point from parent to
+    /// offspring.
+
+    pub fn ancestry_to_dot<W: Write>(&self, id: IndividualId, wr: &mut W) -> io::Result<()> {
+        let ancestry = self.ancestry(id);
+
+        try!(writeln!(wr, "digraph ancestry {{"));
+
+        for record in ancestry.iter() {
+            let lineage = &record.lineage;
+            try!(writeln!(wr,
+                          "  i{} [label=\"#{} gen {}\\n{}\\nfitness {}\"];",
+                          lineage.id.unwrap().0,
+                          lineage.id.unwrap().0,
+                          lineage.generation,
+                          format_operators(&lineage.operators),
+                          format_fitness(record.fitness)));
+        }
+
+        for record in ancestry.iter() {
+            let lineage = &record.lineage;
+            for parent in lineage.parents.iter() {
+                try!(writeln!(wr, "  i{} -> i{};", parent.0, lineage.id.unwrap().0));
+            }
+        }
+
+        writeln!(wr, "}}")
+    }
+
+    /// Writes the ancestry of `id` as CSV, one row per individual. Multiple parents and
+    /// operators are separated by a space.
+
+    pub fn ancestry_to_csv<W: Write>(&self, id: IndividualId, wr: &mut W) -> io::Result<()> {
+        try!(writeln!(wr, "id,generation,parents,operators,fitness"));
+
+        for record in self.ancestry(id) {
+            let lineage = &record.lineage;
+            let parents: Vec<String> = lineage.parents.iter().map(|p| p.0.to_string()).collect();
+            try!(writeln!(wr,
+                          "{},{},{},{},{}",
+                          lineage.id.unwrap().0,
+                          lineage.generation,
+                          parents.join(" "),
+                          format_operators(&lineage.operators),
+                          format_fitness(record.fitness)));
+        }
+
+        Ok(())
+    }
+}
+
+impl Persist for IndividualId {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        self.0.persist(wr)
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        rd.atom().map(IndividualId)
+    }
+}
+
+/// Written as `initial`, `elite-copy`, `crossover` or `(mutate method)`.
+
+impl Persist for Operator {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        match *self {
+            Operator::Initial => write!(wr, "initial "),
+            Operator::EliteCopy => write!(wr, "elite-copy "),
+            Operator::Crossover => write!(wr, "crossover "),
+            Operator::Mutate(method) => {
+                try!(write!(wr, "(mutate "));
+                try!(method.persist(wr));
+                write!(wr, ") ")
+            }
+        }
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        let token = try!(rd.next());
+        match token {
+            "initial" => Ok(Operator::Initial),
+            "elite-copy" => Ok(Operator::EliteCopy),
+            "crossover" => Ok(Operator::Crossover),
+            "(" => {
+                try!(rd.expect("mutate"));
+                let method = try!(Persist::restore(rd));
+                try!(rd.close());
+                Ok(Operator::Mutate(method))
+            }
+            _ => Err(PersistError::Invalid(format!("unknown operator {}", token))),
+        }
+    }
+}
+
+/// Written as `(lineage id (parents...) generation (operators...))`.
+
+impl Persist for Lineage {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "(lineage "));
+        try!(self.id.persist(wr));
+        try!(self.parents.persist(wr));
+        try!(self.generation.persist(wr));
+        try!(self.operators.persist(wr));
+        write!(wr, ") ")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("lineage"));
+        let lineage = Lineage {
+            id: try!(Persist::restore(rd)),
+            parents: try!(Persist::restore(rd)),
+            generation: try!(Persist::restore(rd)),
+            operators: try!(Persist::restore(rd)),
+        };
+        try!(rd.close());
+        Ok(lineage)
+    }
+}
+
+/// Written as `(genealogy next-id (record lineage fitness)...)`.
+
+impl Persist for Genealogy {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "(genealogy "));
+        try!(self.next_id.persist(wr));
+        for record in self.records.values() {
+            try!(write!(wr, "\n  (record "));
+            try!(record.lineage.persist(wr));
+            try!(record.fitness.persist(wr));
+            try!(write!(wr, ") "));
+        }
+        write!(wr, ") ")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("genealogy"));
+        let mut genealogy = Genealogy::new();
+        genealogy.next_id = try!(rd.atom());
+        while !rd.at_close() {
+            try!(rd.open_keyword("record"));
+            let lineage: Lineage = try!(Persist::restore(rd));
+            let fitness = try!(Persist::restore(rd));
+            try!(rd.close());
+
+            let id = match lineage.id {
+                Some(id) if id.0 < genealogy.next_id && !genealogy.records.contains_key(&id) => id,
+                _ => return Err(PersistError::Invalid(format!("record id {:?}", lineage.id))),
+            };
+            genealogy.records.insert(id,
+                                     GenealogyRecord {
+                                         lineage: lineage,
+                                         fitness: fitness,
+                                     });
+        }
+        try!(rd.close());
+        Ok(genealogy)
+    }
+}
+
+fn format_operators(operators: &[Operator]) -> String {
+    let operators: Vec<String> = operators.iter()
+                                          .map(|op| {
+                                              match *op {
+                                                  Operator::Mutate(method) => format!("{:?}", method),
+                                                  _ => format!("{:?}", op),
+                                              }
+                                          })
+                                          .collect();
+    operators.join(" ")
+}
+
+fn format_fitness(fitness: Option<Fitness>) -> String {
+    match fitness {
+        Some(fitness) => fitness.get().to_string(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Genealogy, Lineage, Operator, IndividualId};
+    use mutate::MutateMethod;
+    use fitness::Fitness;
+    use persist::{Persist, restore_from_str};
+
+    #[test]
+    fn test_ancestry() {
+        let mut genealogy = Genealogy::new();
+
+        let mut a = Lineage::initial();
+        let mut b = Lineage::initial();
+        let mut unrelated = Lineage::initial();
+        genealogy.register(&mut a, 0, Some(Fitness::new(1.0)));
+        genealogy.register(&mut b, 0, Some(Fitness::new(2.0)));
+        genealogy.register(&mut unrelated, 0, Some(Fitness::new(0.5)));
+
+        let mut c = Lineage::offspring(&[&b, &a], vec![Operator::Crossover]);
+        genealogy.register(&mut c, 1, Some(Fitness::new(3.0)));
+        let mut d = Lineage::offspring(&[&c, &c],
+                                       vec![Operator::Mutate(MutateMethod::AddNode)]);
+        let d_id = genealogy.register(&mut d, 2, None);
+
+        // registering twice keeps the id
+        assert_eq!(d_id, genealogy.register(&mut d, 3, None));
+        assert_eq!(5, genealogy.len());
+
+        assert_eq!(vec![b.id.unwrap(), a.id.unwrap()], c.parents);
+        assert_eq!(vec![c.id.unwrap()], d.parents);
+        assert_eq!(2, d.generation);
+
+        let ids: Vec<IndividualId> = genealogy.ancestry(d_id)
+                                              .iter()
+                                              .map(|r| r.lineage.id.unwrap())
+                                              .collect();
+        assert_eq!(vec![a.id.unwrap(), b.id.unwrap(), c.id.unwrap(), d_id], ids);
+
+        let mut csv = Vec::new();
+        genealogy.ancestry_to_csv(d_id, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(concat!("id,generation,parents,operators,fitness\n",
+                           "0,0,,Initial,1\n",
+                           "1,0,,Initial,2\n",
+                           "3,1,1 0,Crossover,3\n",
+                           "4,2,3,AddNode,\n"),
+                   csv);
+
+        let mut dot = Vec::new();
+        genealogy.ancestry_to_dot(d_id, &mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.contains("i3 -> i4;"));
+        assert!(dot.contains("i0 -> i3;"));
+        assert!(!dot.contains("i2"));
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut genealogy = Genealogy::new();
+
+        let mut a = Lineage::initial();
+        let mut b = Lineage::initial();
+        let mut unrelated = Lineage::initial();
+        genealogy.register(&mut a, 0, None);
+        genealogy.register(&mut b, 0, None);
+        let unrelated_id = genealogy.register(&mut unrelated, 0, None);
+
+        let mut c = Lineage::offspring(&[&a], vec![Operator::EliteCopy]);
+        let c_id = genealogy.register(&mut c, 1, None);
+        let mut d = Lineage::offspring(&[&c, &b], vec![Operator::Crossover]);
+        let d_id = genealogy.register(&mut d, 2, None);
+
+        // `unrelated` died out without offspring, `c` is still alive.
+        assert_eq!(1, genealogy.prune(vec![d_id, c_id]));
+        assert_eq!(4, genealogy.len());
+        assert!(genealogy.get(unrelated_id).is_none());
+        assert_eq!(4, genealogy.ancestry(d_id).len());
+
+        // new ids do not reuse pruned ones.
+        let mut e = Lineage::initial();
+        assert_eq!(IndividualId(5), genealogy.register(&mut e, 3, None));
+
+        assert_eq!(4, genealogy.prune(vec![e.id.unwrap()]));
+        assert_eq!(1, genealogy.len());
+        assert_eq!(0, genealogy.prune(vec![e.id.unwrap()]));
+    }
+
+    #[test]
+    fn test_persist() {
+        let mut genealogy = Genealogy::new();
+        let mut a = Lineage::initial();
+        genealogy.register(&mut a, 0, Some(Fitness::new(0.25)));
+        let mut b = Lineage::offspring(&[&a],
+                                       vec![Operator::Crossover,
+                                            Operator::Mutate(MutateMethod::DeleteNode)]);
+        genealogy.register(&mut b, 1, None);
+
+        let mut buf = Vec::new();
+        genealogy.persist(&mut buf).unwrap();
+        let restored: Genealogy = restore_from_str(&String::from_utf8(buf).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", genealogy), format!("{:?}", restored));
+
+        // ids continue where the persisted genealogy stopped
+        let mut genealogy = restored;
+        assert_eq!(IndividualId(2), genealogy.register(&mut Lineage::initial(), 2, None));
+
+        // the id of a record has to be below the next id
+        let invalid = "(genealogy 1 (record (lineage (some 1) () 0 (initial)) none))";
+        assert!(restore_from_str::<Genealogy>(invalid).is_err());
+    }
+}
@@ -1,15 +1,43 @@
 use rand::Rng;
 use rand::distributions::{WeightedChoice, Weighted, IndependentSample};
+use persist::{Persist, Reader, PersistError};
+use std::io::{self, Write};
 
 // XXX: Unify Crossover and Mutate.
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MutateMethod {
     ModifyWeight,
     AddConnection,
     EnableConnection,
     DeleteConnection,
+    DeleteNode,
     AddNode,
+    ChangeNodeType,
+    ModifyNodeParams,
+}
+
+/// Written as the name of the variant, e.g. `AddNode`.
+
+impl Persist for MutateMethod {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        write!(wr, "{:?} ", self)
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        let name = try!(rd.next());
+        match name {
+            "ModifyWeight" => Ok(MutateMethod::ModifyWeight),
+            "AddConnection" => Ok(MutateMethod::AddConnection),
+            "EnableConnection" => Ok(MutateMethod::EnableConnection),
+            "DeleteConnection" => Ok(MutateMethod::DeleteConnection),
+            "DeleteNode" => Ok(MutateMethod::DeleteNode),
+            "AddNode" => Ok(MutateMethod::AddNode),
+            "ChangeNodeType" => Ok(MutateMethod::ChangeNodeType),
+            "ModifyNodeParams" => Ok(MutateMethod::ModifyNodeParams),
+            _ => Err(PersistError::Invalid(format!("unknown mutate method {}", name))),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -18,7 +46,10 @@ pub struct MutateMethodWeighting {
     pub w_add_connection: u32,
     pub w_enable_connection: u32,
     pub w_delete_connection: u32,
+    pub w_delete_node: u32,
     pub w_add_node: u32,
+    pub w_change_node_type: u32,
+    pub w_modify_node_params: u32,
 }
 
 impl MutateMethod {
@@ -39,10 +70,98 @@ impl MutateMethod {
                              weight: p.w_delete_connection,
                              item: MutateMethod::DeleteConnection,
                          },
+                         Weighted {
+                             weight: p.w_delete_node,
+                             item: MutateMethod::DeleteNode,
+                         },
                          Weighted {
                              weight: p.w_add_node,
                              item: MutateMethod::AddNode,
+                         },
+                         Weighted {
+                             weight: p.w_change_node_type,
+                             item: MutateMethod::ChangeNodeType,
+                         },
+                         Weighted {
+                             weight: p.w_modify_node_params,
+                             item: MutateMethod::ModifyNodeParams,
                          }];
         WeightedChoice::new(&mut items).ind_sample(rng)
     }
 }
+
+/// Determines how many mutations are applied to a single offspring.
+///
+/// A count of 0 means no mutation on both paths: offspring produced by mutation are then
+/// unmodified copies of a parent, and offspring produced by crossover are not mutated.
+#[derive(Debug, Clone, Copy)]
+pub enum MutationCount {
+    /// Always apply exactly `n` mutations.
+    Fixed(usize),
+    /// Apply `1 + k` mutations, where `k` is Poisson distributed with mean `lambda`.
+    Poisson {
+        lambda: f64,
+    },
+}
+
+impl MutationCount {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        match *self {
+            MutationCount::Fixed(n) => n,
+            MutationCount::Poisson { lambda } => {
+                assert!(lambda >= 0.0);
+                // Knuth's algorithm. Fine for the small means used here.
+                let limit = (-lambda).exp();
+                let mut k = 0;
+                let mut p = rng.gen::<f64>();
+                while p > limit {
+                    k += 1;
+                    p *= rng.gen::<f64>();
+                }
+                1 + k
+            }
+        }
+    }
+}
+
+/// Determines the mutations applied to each offspring.
+#[derive(Debug, Clone, Copy)]
+pub struct MutationSchedule {
+    /// The number of mutations applied to an offspring produced by mutation.
+    pub count: MutationCount,
+
+    /// Also mutate offspring produced by crossover (as in canonical NEAT), using `count`
+    /// mutations.
+    pub mutate_after_crossover: bool,
+}
+
+impl MutationSchedule {
+    /// A single mutation per offspring produced by mutation. Offspring produced by crossover
+    /// are not mutated.
+
+    pub fn single() -> Self {
+        MutationSchedule {
+            count: MutationCount::Fixed(1),
+            mutate_after_crossover: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MutationCount;
+    use rand::{XorShiftRng, SeedableRng};
+
+    #[test]
+    fn test_mutation_count() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        assert_eq!(3, MutationCount::Fixed(3).sample(&mut rng));
+        assert_eq!(1, MutationCount::Poisson { lambda: 0.0 }.sample(&mut rng));
+
+        let poisson = MutationCount::Poisson { lambda: 2.0 };
+        let n = 10000;
+        let sum: usize = (0..n).map(|_| poisson.sample(&mut rng)).sum();
+        let mean = sum as f64 / n as f64;
+        assert!(mean > 2.8 && mean < 3.2);
+    }
+}
@@ -0,0 +1,396 @@
+use std::io::{self, Read, Write, BufWriter};
+use std::str::FromStr;
+use std::fmt;
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+/// Error while restoring a value from it's textual form.
+
+#[derive(Debug)]
+pub enum PersistError {
+    Io(io::Error),
+
+    /// The input ended in the middle of a value.
+    UnexpectedEnd,
+
+    /// A token other than the expected one was found.
+    Unexpected {
+        expected: String,
+        found: String,
+    },
+
+    /// The input is well-formed, but describes an invalid value.
+    Invalid(String),
+
+    /// The input was written in a version of the format we cannot read.
+    UnsupportedVersion(usize),
+}
+
+impl From<io::Error> for PersistError {
+    fn from(err: io::Error) -> Self {
+        PersistError::Io(err)
+    }
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PersistError::Io(ref err) => write!(f, "{}", err),
+            PersistError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            PersistError::Unexpected { ref expected, ref found } => {
+                write!(f, "expected `{}`, found `{}`", expected, found)
+            }
+            PersistError::Invalid(ref msg) => write!(f, "invalid value: {}", msg),
+            PersistError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+        }
+    }
+}
+
+impl Error for PersistError {
+    fn description(&self) -> &str {
+        match *self {
+            PersistError::Io(ref err) => err.description(),
+            PersistError::UnexpectedEnd => "unexpected end of input",
+            PersistError::Unexpected { .. } => "unexpected token",
+            PersistError::Invalid(..) => "invalid value",
+            PersistError::UnsupportedVersion(..) => "unsupported format version",
+        }
+    }
+}
+
+/// Values which can be written as S-expression and read back.
+///
+/// Compound values are written as a list starting with a keyword, e.g. `(lineage ...)`, plain
+/// values like numbers as a single atom. Floating point numbers are written with enough digits
+/// to restore them exactly.
+
+pub trait Persist: Sized {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()>;
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError>;
+}
+
+/// Splits an S-expression into tokens. `;` starts a comment, which extends to the end of the
+/// line.
+
+pub struct Reader<'a> {
+    input: &'a str,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(input: &'a str) -> Reader<'a> {
+        Reader { input: input }
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            self.input = self.input.trim_left();
+            if self.input.starts_with(';') {
+                self.input = match self.input.find('\n') {
+                    Some(pos) => &self.input[pos..],
+                    None => "",
+                };
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Returns the next token without consuming it.
+
+    pub fn peek(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let input = self.input;
+        if input.is_empty() {
+            return None;
+        }
+        if input.starts_with('(') || input.starts_with(')') {
+            return Some(&input[..1]);
+        }
+        let end = input.find(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == ';')
+                       .unwrap_or(input.len());
+        Some(&input[..end])
+    }
+
+    /// Consumes and returns the next token.
+
+    pub fn next(&mut self) -> Result<&'a str, PersistError> {
+        match self.peek() {
+            Some(token) => {
+                self.input = &self.input[token.len()..];
+                Ok(token)
+            }
+            None => Err(PersistError::UnexpectedEnd),
+        }
+    }
+
+    /// Consumes the next token, which has to be `expected`.
+
+    pub fn expect(&mut self, expected: &str) -> Result<(), PersistError> {
+        let token = try!(self.next());
+        if token == expected {
+            Ok(())
+        } else {
+            Err(PersistError::Unexpected {
+                expected: expected.to_string(),
+                found: token.to_string(),
+            })
+        }
+    }
+
+    /// Consumes the start of a list, i.e. `(`.
+
+    pub fn open(&mut self) -> Result<(), PersistError> {
+        self.expect("(")
+    }
+
+    /// Consumes the start of a list which begins with `keyword`.
+
+    pub fn open_keyword(&mut self, keyword: &str) -> Result<(), PersistError> {
+        try!(self.open());
+        self.expect(keyword)
+    }
+
+    /// Consumes the end of a list, i.e. `)`.
+
+    pub fn close(&mut self) -> Result<(), PersistError> {
+        self.expect(")")
+    }
+
+    /// Returns `true` if the current list is at it's end.
+
+    pub fn at_close(&mut self) -> bool {
+        self.peek() == Some(")")
+    }
+
+    /// Consumes the next token and parses it as `T`.
+
+    pub fn atom<T: FromStr>(&mut self) -> Result<T, PersistError> {
+        let token = try!(self.next());
+        if token == "(" || token == ")" {
+            return Err(PersistError::Unexpected {
+                expected: "atom".to_string(),
+                found: token.to_string(),
+            });
+        }
+        token.parse().map_err(|_| PersistError::Invalid(token.to_string()))
+    }
+
+    /// Returns `true` if all input was consumed.
+
+    pub fn is_at_end(&mut self) -> bool {
+        self.peek().is_none()
+    }
+
+    /// Fails unless all input was consumed.
+
+    pub fn expect_end(&mut self) -> Result<(), PersistError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => {
+                Err(PersistError::Unexpected {
+                    expected: "end of input".to_string(),
+                    found: token.to_string(),
+                })
+            }
+        }
+    }
+}
+
+macro_rules! persist_atom {
+    ($t:ty) => {
+        impl Persist for $t {
+            fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+                write!(wr, "{} ", self)
+            }
+
+            fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+                rd.atom()
+            }
+        }
+    }
+}
+
+persist_atom!(usize);
+persist_atom!(u64);
+persist_atom!(f64);
+persist_atom!(bool);
+
+/// Written as `none` or `(some value)`.
+
+impl<T: Persist> Persist for Option<T> {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        match *self {
+            Some(ref value) => {
+                try!(write!(wr, "(some "));
+                try!(value.persist(wr));
+                write!(wr, ") ")
+            }
+            None => write!(wr, "none "),
+        }
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        if rd.peek() == Some("none") {
+            try!(rd.next());
+            return Ok(None);
+        }
+        try!(rd.open_keyword("some"));
+        let value = try!(T::restore(rd));
+        try!(rd.close());
+        Ok(Some(value))
+    }
+}
+
+/// Written as list of it's elements.
+
+impl<T: Persist> Persist for Vec<T> {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "("));
+        for value in self.iter() {
+            try!(value.persist(wr));
+        }
+        write!(wr, ") ")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open());
+        let mut values = Vec::new();
+        while !rd.at_close() {
+            values.push(try!(T::restore(rd)));
+        }
+        try!(rd.close());
+        Ok(values)
+    }
+}
+
+impl<A: Persist, B: Persist> Persist for (A, B) {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "("));
+        try!(self.0.persist(wr));
+        try!(self.1.persist(wr));
+        write!(wr, ") ")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open());
+        let a = try!(A::restore(rd));
+        let b = try!(B::restore(rd));
+        try!(rd.close());
+        Ok((a, b))
+    }
+}
+
+/// Restores a `T` from `input`, which must not contain anything else.
+
+pub fn restore_from_str<T: Persist>(input: &str) -> Result<T, PersistError> {
+    let mut rd = Reader::new(input);
+    let value = try!(T::restore(&mut rd));
+    try!(rd.expect_end());
+    Ok(value)
+}
+
+/// The version of the archive format written by `save`. It changes whenever the textual form
+/// of a type changes in a way older versions cannot read.
+pub const ARCHIVE_VERSION: usize = 1;
+
+/// Writes `value` as archive `(neat-archive version value)`, e.g. to keep the champion of a
+/// run and load it into a later one.
+
+pub fn save<T: Persist, W: Write>(value: &T, wr: &mut W) -> io::Result<()> {
+    try!(write!(wr, "(neat-archive {}\n", ARCHIVE_VERSION));
+    try!(value.persist(wr));
+    write!(wr, ")\n")
+}
+
+/// Restores a value written by `save`.
+
+pub fn load<T: Persist>(input: &str) -> Result<T, PersistError> {
+    let mut rd = Reader::new(input);
+    try!(rd.open_keyword("neat-archive"));
+    let version: usize = try!(rd.atom());
+    if version != ARCHIVE_VERSION {
+        return Err(PersistError::UnsupportedVersion(version));
+    }
+    let value = try!(T::restore(&mut rd));
+    try!(rd.close());
+    try!(rd.expect_end());
+    Ok(value)
+}
+
+/// Same as `save`, but writes to the file at `path`.
+
+pub fn save_file<T: Persist, P: AsRef<Path>>(value: &T, path: P) -> io::Result<()> {
+    let mut wr = BufWriter::new(try!(File::create(path)));
+    try!(save(value, &mut wr));
+    wr.flush()
+}
+
+/// Same as `load`, but reads from the file at `path`.
+
+pub fn load_file<T: Persist, P: AsRef<Path>>(path: P) -> Result<T, PersistError> {
+    let mut input = String::new();
+    try!(try!(File::open(path)).read_to_string(&mut input));
+    load(&input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Persist, Reader, PersistError, restore_from_str, save, load};
+
+    fn to_string<T: Persist>(value: &T) -> String {
+        let mut buf = Vec::new();
+        value.persist(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value: Vec<(usize, Option<f64>)> = vec![(1, Some(0.1)),
+                                                    (2, None),
+                                                    (3, Some(-1.0 / 3.0))];
+        let s = to_string(&value);
+        assert_eq!("((1 (some 0.1 ) ) (2 none ) (3 (some -0.3333333333333333 ) ) ) ", s);
+        let restored: Vec<(usize, Option<f64>)> = restore_from_str(&s).unwrap();
+        assert_eq!(value, restored);
+    }
+
+    #[test]
+    fn test_reader() {
+        let mut rd = Reader::new("; header\n(a (b) 1.5)");
+        rd.open_keyword("a").unwrap();
+        rd.open_keyword("b").unwrap();
+        assert!(rd.at_close());
+        rd.close().unwrap();
+        assert_eq!(1.5, rd.atom::<f64>().unwrap());
+        rd.close().unwrap();
+        assert!(rd.is_at_end());
+
+        match restore_from_str::<usize>("1 2") {
+            Err(PersistError::Unexpected { .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match restore_from_str::<Vec<usize>>("(1 2") {
+            Err(PersistError::UnexpectedEnd) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_archive() {
+        let value: Vec<u64> = vec![1, 2, 3];
+        let mut buf = Vec::new();
+        save(&value, &mut buf).unwrap();
+        let archive = String::from_utf8(buf).unwrap();
+        assert_eq!("(neat-archive 1\n(1 2 3 ) )\n", archive);
+        assert_eq!(value, load::<Vec<u64>>(&archive).unwrap());
+
+        match load::<Vec<u64>>("(neat-archive 2 (1 2 3))") {
+            Err(PersistError::UnsupportedVersion(2)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(load::<Vec<u64>>("(1 2 3)").is_err());
+    }
+}
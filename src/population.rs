@@ -1,9 +1,12 @@
 use fitness::Fitness;
-use traits::{Genotype, Distance, Mate, FitnessEval};
+use traits::{Genotype, Distance, Mate, StagedMate, FitnessEval};
 use prob::probabilistic_round;
 use distribute::DistributeInterval;
+use lineage::{Lineage, Genealogy, IndividualId, Contributors};
+use seed::SeedStream;
+use persist::{Persist, Reader, PersistError};
 
-use rand::Rng;
+use rand::{Rng, XorShiftRng};
 use closed01::Closed01;
 use std::marker::PhantomData;
 use std::fmt::Debug;
@@ -11,11 +14,15 @@ use std::cmp;
 use std::mem;
 use std::f64::{INFINITY, NEG_INFINITY};
 use rayon::par_iter::*;
+use std::io::{self, Read, Write, BufWriter};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub struct Individual<T: Debug + Genotype> {
     fitness: Option<Fitness>,
     genome: Box<T>,
+    lineage: Lineage,
 }
 
 impl<T: Debug + Genotype> Individual<T> {
@@ -30,6 +37,50 @@ impl<T: Debug + Genotype> Individual<T> {
     pub fn genome(&self) -> &T {
         &self.genome
     }
+
+    pub fn lineage(&self) -> &Lineage {
+        &self.lineage
+    }
+
+    /// Takes the genome, e.g. to add an archived individual to the population of a new run.
+
+    pub fn into_genome(self) -> Box<T> {
+        self.genome
+    }
+
+    /// Copies `self` as-is into the next generation. Only the lineage changes.
+
+    fn into_elite_copy(self) -> Self {
+        let lineage = self.lineage.elite_copy();
+        Individual {
+            fitness: self.fitness,
+            genome: self.genome,
+            lineage: lineage,
+        }
+    }
+}
+
+/// Written as `(individual fitness lineage genome)`.
+
+impl<T: Debug + Genotype + Persist> Persist for Individual<T> {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "(individual "));
+        try!(self.fitness.persist(wr));
+        try!(self.lineage.persist(wr));
+        try!(self.genome.persist(wr));
+        write!(wr, ")\n")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("individual"));
+        let individual = Individual {
+            fitness: try!(Persist::restore(rd)),
+            lineage: try!(Persist::restore(rd)),
+            genome: Box::new(try!(T::restore(rd))),
+        };
+        try!(rd.close());
+        Ok(individual)
+    }
 }
 
 pub trait Rating { }
@@ -172,11 +223,40 @@ impl<T: Genotype + Debug> Niche<T> {
                                       mate,
                                       &mut new_unrated_population,
                                       &mut self.population,
+                                      None,
                                       rng);
 
         self.population.append(new_unrated_population.rate_par(fitness_eval));
     }
 
+    /// Same as `reproduce_locally`, but draws all random numbers from streams derived from
+    /// `seed`.
+
+    pub fn reproduce_locally_seeded<M, F>(&mut self,
+                                          new_niche_size: f64,
+                                          elite_percentage: Closed01<f64>,
+                                          selection_percentage: Closed01<f64>,
+                                          mate: &mut M,
+                                          fitness_eval: &F,
+                                          seed: &SeedStream)
+        where M: Mate<T>,
+              F: FitnessEval<T>
+    {
+        let old_population = mem::replace(&mut self.population, Population::new());
+        let mut new_unrated_population: Population<T, Unrated> = Population::new();
+
+        old_population.reproduce_into(new_niche_size,
+                                      elite_percentage,
+                                      selection_percentage,
+                                      mate,
+                                      &mut new_unrated_population,
+                                      &mut self.population,
+                                      Some(&seed.split(1)),
+                                      &mut seed.split(0).rng());
+
+        self.population.append(new_unrated_population.rate_par(fitness_eval));
+    }
+
 
     pub fn len(&self) -> usize {
         self.population.len()
@@ -355,6 +435,24 @@ impl<T: Genotype + Debug> Niches<T> {
         self.niches.len()
     }
 
+    /// Registers all individuals of all niches not yet known to `genealogy`.
+
+    pub fn register_lineage(&mut self, genealogy: &mut Genealogy, generation: usize) {
+        for niche in self.niches.iter_mut() {
+            niche.population.register_lineage(genealogy, generation);
+        }
+    }
+
+    /// The ids of all registered individuals of all niches.
+
+    pub fn lineage_ids(&self) -> Vec<IndividualId> {
+        let mut ids = Vec::new();
+        for niche in self.niches.iter() {
+            ids.extend(niche.population.lineage_ids());
+        }
+        ids
+    }
+
     /// Add a new niche to the `Niches`.
 
     pub fn add_niche(&mut self, niche: Niche<T>) {
@@ -464,6 +562,7 @@ impl<T: Genotype + Debug> Niches<T> {
                                             mate,
                                             &mut new_unrated_population,
                                             &mut new_rated_population,
+                                            None,
                                             rng);
         }
 
@@ -471,6 +570,97 @@ impl<T: Genotype + Debug> Niches<T> {
     }
 }
 
+/// Written as `(stat min max mean)`.
+
+impl Persist for NicheFitnessStat {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "(stat "));
+        try!(self.min_fitness.persist(wr));
+        try!(self.max_fitness.persist(wr));
+        try!(self.mean_fitness.persist(wr));
+        write!(wr, ") ")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("stat"));
+        let stat = NicheFitnessStat {
+            min_fitness: try!(Persist::restore(rd)),
+            max_fitness: try!(Persist::restore(rd)),
+            mean_fitness: try!(Persist::restore(rd)),
+        };
+        try!(rd.close());
+        Ok(stat)
+    }
+}
+
+/// Written as `(niche centroid (fitness-log stat...) population)`.
+
+impl<T: Genotype + Debug + Persist> Persist for Niche<T> {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "(niche "));
+        try!(self.centroid.persist(wr));
+        try!(write!(wr, "\n(fitness-log "));
+        for stat in self.fitness_log.iter() {
+            try!(stat.persist(wr));
+        }
+        try!(write!(wr, ")\n"));
+        try!(self.population.persist(wr));
+        write!(wr, ")\n")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("niche"));
+        let centroid: Option<usize> = try!(Persist::restore(rd));
+
+        try!(rd.open_keyword("fitness-log"));
+        let mut fitness_log = Vec::new();
+        while !rd.at_close() {
+            fitness_log.push(try!(NicheFitnessStat::restore(rd)));
+        }
+        try!(rd.close());
+
+        let population: Population<T, Rated> = try!(Persist::restore(rd));
+        try!(rd.close());
+
+        if population.len() == 0 {
+            return Err(PersistError::Invalid("empty niche".to_string()));
+        }
+        if let Some(centroid) = centroid {
+            if centroid >= population.len() {
+                return Err(PersistError::Invalid(format!("centroid {}", centroid)));
+            }
+        }
+
+        Ok(Niche {
+            population: population,
+            centroid: centroid,
+            fitness_log: fitness_log,
+        })
+    }
+}
+
+/// Written as `(niches niche...)`.
+
+impl<T: Genotype + Debug + Persist> Persist for Niches<T> {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "(niches\n"));
+        for niche in self.niches.iter() {
+            try!(niche.persist(wr));
+        }
+        write!(wr, ")\n")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("niches"));
+        let mut niches = Niches::new();
+        while !rd.at_close() {
+            niches.add_niche(try!(Persist::restore(rd)));
+        }
+        try!(rd.close());
+        Ok(niches)
+    }
+}
+
 impl<T: Genotype + Debug, R: Rating> Population<T, R> {
     pub fn new() -> Population<T, R> {
         Population {
@@ -489,13 +679,58 @@ impl<T: Genotype + Debug, R: Rating> Population<T, R> {
     pub fn len(&self) -> usize {
         self.individuals.len()
     }
+
+    /// Registers all individuals not yet known to `genealogy` as born in `generation`.
+
+    pub fn register_lineage(&mut self, genealogy: &mut Genealogy, generation: usize) {
+        for ind in self.individuals.iter_mut() {
+            genealogy.register(&mut ind.lineage, generation, ind.fitness);
+        }
+    }
+
+    /// The ids of all registered individuals.
+
+    pub fn lineage_ids(&self) -> Vec<IndividualId> {
+        self.individuals.iter().filter_map(|ind| ind.lineage.id).collect()
+    }
+}
+
+/// Written as `(population individual...)`. Every individual has to be rated.
+
+impl<T: Genotype + Debug + Persist> Persist for Population<T, Rated> {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        try!(write!(wr, "(population\n"));
+        for ind in self.individuals.iter() {
+            try!(ind.persist(wr));
+        }
+        write!(wr, ")\n")
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("population"));
+        let mut pop = Population::new();
+        while !rd.at_close() {
+            let ind: Individual<T> = try!(Persist::restore(rd));
+            if !ind.has_fitness() {
+                return Err(PersistError::Invalid("unrated individual".to_string()));
+            }
+            pop.add_individual(ind);
+        }
+        try!(rd.close());
+        Ok(pop)
+    }
 }
 
 impl<T: Genotype + Debug> Population<T, Unrated> {
     pub fn add_genome(&mut self, genome: Box<T>) {
+        self.add_genome_with_lineage(genome, Lineage::initial());
+    }
+
+    pub fn add_genome_with_lineage(&mut self, genome: Box<T>, lineage: Lineage) {
         self.individuals.push(Individual {
             fitness: None,
             genome: genome,
+            lineage: lineage,
         });
     }
 
@@ -837,8 +1072,13 @@ impl<T: Genotype + Debug> Population<T, RatedSorted> {
 
     /// Create a single offspring Genome by selecting random parents
     /// from the best `select_size` individuals of the populations.
+    /// Returns the offspring together with it's lineage.
 
-    fn create_single_offspring<R, M>(&self, select_size: usize, mate: &mut M, rng: &mut R) -> T
+    fn create_single_offspring<R, M>(&self,
+                                     select_size: usize,
+                                     mate: &mut M,
+                                     rng: &mut R)
+                                     -> (T, Lineage)
         where R: Rng,
               M: Mate<T>
     {
@@ -865,10 +1105,25 @@ impl<T: Genotype + Debug> Population<T, RatedSorted> {
 
         debug_assert!(parent1 <= parent2);
 
-        mate.mate(&self.individuals[parent1].genome,
-                  &self.individuals[parent2].genome,
-                  parent1 == parent2,
-                  rng)
+        let left = &self.individuals[parent1];
+        let right = &self.individuals[parent2];
+        debug_assert!(left.fitness() >= right.fitness());
+
+        let mut operators = Vec::new();
+        let (offspring, contributors) = mate.mate_traced(&left.genome,
+                                                         &right.genome,
+                                                         left.fitness(),
+                                                         right.fitness(),
+                                                         parent1 == parent2,
+                                                         &mut operators,
+                                                         rng);
+
+        let parents = match contributors {
+            Contributors::Left => vec![&left.lineage],
+            Contributors::Right => vec![&right.lineage],
+            Contributors::Both => vec![&left.lineage, &right.lineage],
+        };
+        (offspring, Lineage::offspring(&parents, operators))
     }
 }
 
@@ -951,6 +1206,7 @@ impl<T: Genotype + Debug> Population<T, Rated> {
                             mate,
                             &mut new_unrated_population,
                             &mut new_rated_population,
+                            None,
                             rng);
 
         return (new_rated_population, new_unrated_population);
@@ -962,6 +1218,9 @@ impl<T: Genotype + Debug> Population<T, Rated> {
     /// We first sort the population according to it's fitness values.
     /// Then, `selection_percentage` of the best genomes are allowed to mate and produce offspring.
     /// Then, `elite_percentage` of the best genomes is always copied into the new generation.
+    ///
+    /// If `offspring_seed` is given, the `i`th offspring is created using the random number
+    /// stream `offspring_seed.split(i)` instead of `rng`.
 
     fn reproduce_into<M, R>(self,
                             // The expected size of the new population
@@ -975,6 +1234,7 @@ impl<T: Genotype + Debug> Population<T, Rated> {
                             mate: &mut M,
                             new_unrated_population: &mut Population<T, Unrated>,
                             new_rated_population: &mut Population<T, Rated>,
+                            offspring_seed: Option<&SeedStream>,
                             rng: &mut R)
         where M: Mate<T>,
               R: Rng
@@ -998,14 +1258,23 @@ impl<T: Genotype + Debug> Population<T, Rated> {
         // at first produce `offspring_size` individuals from the top `select_size`
         // individuals.
         if select_size > 0 {
-            for _ in 0..offspring_size {
-                let offspring = sorted_pop.create_single_offspring(select_size, mate, rng);
-                new_unrated_population.add_genome(Box::new(offspring));
+            for i in 0..offspring_size {
+                let (offspring, lineage) = match offspring_seed {
+                    Some(seed) => {
+                        sorted_pop.create_single_offspring(select_size,
+                                                           mate,
+                                                           &mut seed.split(i as u64).rng())
+                    }
+                    None => sorted_pop.create_single_offspring(select_size, mate, rng),
+                };
+                new_unrated_population.add_genome_with_lineage(Box::new(offspring), lineage);
             }
         }
 
         // then copy the elites
-        new_rated_population.merge(sorted_pop, elite_size);
+        for ind in sorted_pop.individuals.into_iter().take(elite_size) {
+            new_rated_population.add_individual(ind.into_elite_copy());
+        }
     }
 }
 
@@ -1016,6 +1285,7 @@ pub struct NicheRunner<'a, T, F>
     niches: Niches<T>,
     fitness: &'a F,
     current_iteration: usize,
+    genealogy: Genealogy,
 }
 
 impl<'a, T, F> NicheRunner<'a, T, F>
@@ -1027,9 +1297,32 @@ impl<'a, T, F> NicheRunner<'a, T, F>
             niches: Niches::new(),
             fitness: fitness,
             current_iteration: 0,
+            genealogy: Genealogy::new(),
         }
     }
 
+    /// The lineage of every individual seen by this runner. Use e.g.
+    /// `genealogy().ancestry_to_dot(...)` with the id of the `best_individual()` to export the
+    /// ancestry of the champion.
+
+    pub fn genealogy(&self) -> &Genealogy {
+        &self.genealogy
+    }
+
+    fn register_lineage(&mut self) {
+        self.niches.register_lineage(&mut self.genealogy, self.current_iteration);
+    }
+
+    /// Removes the lineage of all individuals which are no ancestor of the current population
+    /// from the `genealogy()`. Call it every few iterations to keep the genealogy (and the
+    /// checkpoints, which include it) from growing with each iteration. Returns the number of
+    /// removed records.
+
+    pub fn prune_genealogy(&mut self) -> usize {
+        let live = self.niches.lineage_ids();
+        self.genealogy.prune(live)
+    }
+
     pub fn best_individual(&self) -> &Individual<T> {
         assert!(self.niches.num_niches() > 0);
         self.niches.best_individual()
@@ -1057,6 +1350,7 @@ impl<'a, T, F> NicheRunner<'a, T, F>
 
     pub fn add_unrated_population_as_niche(&mut self, pop: Population<T, Unrated>) {
         self.niches.add_niche(Niche::from_population(pop.rate_par(self.fitness)));
+        self.register_lineage();
     }
 
     pub fn partition_threshold<C, R>(&mut self,
@@ -1181,7 +1475,7 @@ impl<'a, T, F> NicheRunner<'a, T, F>
         let total_mean = self.niches.total_mean();
         let num_niches = self.niches.num_niches();
 
-        // XXX: do in parallel
+        // see `reproduce_niche_locally_par` for a parallel version.
         for niche in self.niches.niches.iter_mut() {
             let new_niche_size = niche.determine_new_niche_size(total_mean,
                                                                 num_niches,
@@ -1193,6 +1487,109 @@ impl<'a, T, F> NicheRunner<'a, T, F>
                                     self.fitness,
                                     rng);
         }
+
+        self.register_lineage();
+    }
+
+    /// Same as `reproduce_niche_locally`, but reproduces the niches in parallel.
+    ///
+    /// Each niche uses it's own clone of `mate` and it's own random number generator, seeded
+    /// from `rng`. To keep innovations consistent between the niches, the clones of `mate`
+    /// should share a `ConcurrentInnovationCache`.
+    ///
+    /// The result is not reproducible, even for a seeded `rng`: Which niche first creates a
+    /// structural innovation, and as such which innovation numbers are assigned, depends on the
+    /// scheduling of the threads. Use `reproduce_niche_locally_seeded` for reproducible runs.
+
+    pub fn reproduce_niche_locally_par<M, R>(&mut self,
+                                             new_total_pop_size: usize,
+                                             elite_percentage: Closed01<f64>,
+                                             selection_percentage: Closed01<f64>,
+                                             mate: &M,
+                                             rng: &mut R)
+        where M: Mate<T> + Clone + Send,
+              R: Rng
+    {
+        let total_mean = self.niches.total_mean();
+        let num_niches = self.niches.num_niches();
+        let fitness = self.fitness;
+
+        {
+            let mut jobs: Vec<(&mut Niche<T>, XorShiftRng, M)> =
+                self.niches
+                    .niches
+                    .iter_mut()
+                    .map(|niche| (niche, rng.gen(), mate.clone()))
+                    .collect();
+
+            jobs.par_iter_mut().for_each(|job| {
+                let (ref mut niche, ref mut niche_rng, ref mut niche_mate) = *job;
+                let new_niche_size = niche.determine_new_niche_size(total_mean,
+                                                                    num_niches,
+                                                                    new_total_pop_size);
+                niche.reproduce_locally(new_niche_size,
+                                        elite_percentage,
+                                        selection_percentage,
+                                        niche_mate,
+                                        fitness,
+                                        niche_rng);
+            });
+        }
+
+        self.register_lineage();
+    }
+
+    /// Reproduces the niches in parallel, like `reproduce_niche_locally_par`, but the result only
+    /// depends on `seed`, and not on the number of threads.
+    ///
+    /// Niche `i` draws it's random numbers from `seed.split(i)`. Each niche uses it's own stage
+    /// of `mate`, which are committed in niche order afterwards. Use a different `seed` for each
+    /// generation, e.g. `master_seed.split(generation)`.
+
+    pub fn reproduce_niche_locally_seeded<M>(&mut self,
+                                             new_total_pop_size: usize,
+                                             elite_percentage: Closed01<f64>,
+                                             selection_percentage: Closed01<f64>,
+                                             mate: &mut M,
+                                             seed: &SeedStream)
+        where M: StagedMate<T>
+    {
+        let total_mean = self.niches.total_mean();
+        let num_niches = self.niches.num_niches();
+        let fitness = self.fitness;
+
+        let stages: Vec<M::Stage> = {
+            let mut jobs: Vec<(&mut Niche<T>, SeedStream, M::Stage)> =
+                self.niches
+                    .niches
+                    .iter_mut()
+                    .zip(mate.stage(num_niches))
+                    .enumerate()
+                    .map(|(i, (niche, stage))| (niche, seed.split(i as u64), stage))
+                    .collect();
+
+            jobs.par_iter_mut().for_each(|job| {
+                let (ref mut niche, ref niche_seed, ref mut stage) = *job;
+                let new_niche_size = niche.determine_new_niche_size(total_mean,
+                                                                    num_niches,
+                                                                    new_total_pop_size);
+                niche.reproduce_locally_seeded(new_niche_size,
+                                               elite_percentage,
+                                               selection_percentage,
+                                               stage,
+                                               fitness,
+                                               niche_seed);
+            });
+
+            jobs.into_iter().map(|(_, _, stage)| stage).collect()
+        };
+
+        for (niche, stage) in self.niches.niches.iter_mut().zip(stages) {
+            mate.commit(stage,
+                        niche.population.individuals.iter_mut().map(|ind| &mut *ind.genome));
+        }
+
+        self.register_lineage();
     }
 
     pub fn reproduce_global<M, R>(&mut self,
@@ -1213,6 +1610,7 @@ impl<'a, T, F> NicheRunner<'a, T, F>
 
         new_rated.append(new_unrated.rate_par(self.fitness));
         self.niches = Niches::from_single_population(new_rated);
+        self.register_lineage();
     }
 
     pub fn into_population(self) -> Population<T, Rated> {
@@ -1220,6 +1618,97 @@ impl<'a, T, F> NicheRunner<'a, T, F>
     }
 }
 
+/// The version of the format written by `NicheRunner::write_checkpoint`.
+const CHECKPOINT_VERSION: usize = 1;
+
+impl<'a, T, F> NicheRunner<'a, T, F>
+    where T: Genotype + Debug + Persist + 'a,
+          F: FitnessEval<T> + 'a
+{
+    /// Writes the state of the run together with the additional `state` the run depends on,
+    /// usually the `GlobalInnovationCache` and the master `SeedStream`.
+    ///
+    /// The state of a random number generator cannot be written. To continue a run with the
+    /// same random numbers, it has to derive all random numbers of an iteration from a
+    /// `SeedStream`, and include the master stream in `state`. E.g. with
+    /// `let seed = master.split(current_iteration)`, reproduce with
+    /// `reproduce_niche_locally_seeded(..., &seed.split(0))` and pass `&mut seed.split(1).rng()`
+    /// to `redistribute_niches_with_no_improvement` or the `partition_*` methods.
+
+    pub fn write_checkpoint<W: Write, S: Persist>(&self,
+                                                  state: &S,
+                                                  wr: &mut W)
+                                                  -> io::Result<()> {
+        try!(write!(wr, "(neat-checkpoint {}\n", CHECKPOINT_VERSION));
+        try!(write!(wr, "(iteration {})\n", self.current_iteration));
+        try!(self.niches.persist(wr));
+        try!(self.genealogy.persist(wr));
+        try!(write!(wr, "\n"));
+        try!(state.persist(wr));
+        write!(wr, ")\n")
+    }
+
+    /// Restores a run written by `write_checkpoint`, together with it's additional `state`.
+
+    pub fn read_checkpoint<S: Persist>(fitness: &'a F,
+                                       input: &str)
+                                       -> Result<(Self, S), PersistError> {
+        let mut rd = Reader::new(input);
+        try!(rd.open_keyword("neat-checkpoint"));
+        let version: usize = try!(rd.atom());
+        if version != CHECKPOINT_VERSION {
+            return Err(PersistError::UnsupportedVersion(version));
+        }
+
+        try!(rd.open_keyword("iteration"));
+        let current_iteration = try!(rd.atom());
+        try!(rd.close());
+
+        let runner = NicheRunner {
+            niches: try!(Persist::restore(&mut rd)),
+            fitness: fitness,
+            current_iteration: current_iteration,
+            genealogy: try!(Persist::restore(&mut rd)),
+        };
+        let state = try!(S::restore(&mut rd));
+        try!(rd.close());
+
+        try!(rd.expect_end());
+        Ok((runner, state))
+    }
+
+    /// Writes a checkpoint to the file at `path`. The checkpoint is first written to a
+    /// temporary file next to it, so that an existing checkpoint is only replaced by a complete
+    /// one.
+
+    pub fn save_checkpoint<P: AsRef<Path>, S: Persist>(&self,
+                                                       state: &S,
+                                                       path: P)
+                                                       -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        {
+            let mut wr = BufWriter::new(try!(File::create(&tmp_path)));
+            try!(self.write_checkpoint(state, &mut wr));
+            try!(wr.flush());
+        }
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Restores a run from the checkpoint file at `path`.
+
+    pub fn load_checkpoint<P: AsRef<Path>, S: Persist>(fitness: &'a F,
+                                                       path: P)
+                                                       -> Result<(Self, S), PersistError> {
+        let mut input = String::new();
+        try!(try!(File::open(path)).read_to_string(&mut input));
+        Self::read_checkpoint(fitness, &input)
+    }
+}
+
 pub struct Runner<'a, T, C, M, F>
     where T: Genotype + Debug,
           C: Distance<T> + 'a,
@@ -1276,3 +1765,459 @@ impl<'a, T, C, M, F> Runner<'a, T, C, M, F>
         return (iteration, current_rated_pop);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{NicheRunner, Population, Individual, Unrated, Rated};
+    use genomes::acyclic_network::{Genome, NodeType, GlobalInnovationCache, Mater,
+                                   ElementStrategy, GenomeBuilder, InitialConnectivity,
+                                   ConcurrentInnovationCache, GlobalCache, NodeInnovation,
+                                   LinkInnovation, GenomeDistance};
+    use persist::{Persist, Reader, PersistError, save, load};
+    use lineage::{IndividualId, Operator};
+    use traits::FitnessEval;
+    use fitness::Fitness;
+    use mutate::{MutateMethodWeighting, MutationSchedule};
+    use crossover::{ProbabilisticCrossover, BlendMethod};
+    use weight::{Weight, WeightRange, WeightPerturbanceMethod};
+    use seed::SeedStream;
+    use prob::Prob;
+    use closed01::Closed01;
+    use rand::Rng;
+    use rayon::{ThreadPool, Configuration};
+    use std::marker::PhantomData;
+    use std::io::{self, Write};
+    use std::collections::BTreeMap;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Neuron {
+        Input,
+        Hidden,
+        Output,
+    }
+
+    impl NodeType for Neuron {
+        fn accept_incoming_links(&self) -> bool {
+            *self != Neuron::Input
+        }
+        fn accept_outgoing_links(&self) -> bool {
+            *self != Neuron::Output
+        }
+    }
+
+    impl Persist for Neuron {
+        fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+            write!(wr, "{:?} ", self)
+        }
+
+        fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+            match try!(rd.next()) {
+                "Input" => Ok(Neuron::Input),
+                "Hidden" => Ok(Neuron::Hidden),
+                "Output" => Ok(Neuron::Output),
+                token => Err(PersistError::Invalid(token.to_string())),
+            }
+        }
+    }
+
+    struct ES;
+
+    impl ElementStrategy<Neuron> for ES {
+        fn link_weight_range(&self) -> WeightRange {
+            WeightRange::bipolar(1.0)
+        }
+
+        fn full_link_weight(&self) -> Weight {
+            WeightRange::bipolar(1.0).high()
+        }
+
+        fn random_node_type<R: Rng>(&self, _rng: &mut R) -> Neuron {
+            Neuron::Hidden
+        }
+    }
+
+    /// Rewards larger genomes.
+    struct Size;
+
+    impl FitnessEval<Genome<Neuron>> for Size {
+        fn fitness(&self, genome: &Genome<Neuron>) -> Fitness {
+            Fitness::new((genome.node_count() + genome.link_count()) as f64)
+        }
+    }
+
+    fn initial_runner<'a>(fitness: &'a Size,
+                          cache: &mut GlobalInnovationCache,
+                          master: &SeedStream)
+                          -> NicheRunner<'a, Genome<Neuron>, Size> {
+        let mut rng = master.split(0).rng();
+        let builder = GenomeBuilder::new(vec![Neuron::Input; 3],
+                                         vec![Neuron::Output; 2],
+                                         None,
+                                         cache);
+
+        let mut runner = NicheRunner::new(fitness);
+        for _ in 0..3 {
+            let mut pop = Population::<_, Unrated>::new();
+            for _ in 0..10 {
+                pop.add_genome(Box::new(builder.build(InitialConnectivity::Sparse(Prob::new(0.5)),
+                                                      &ES.link_weight_range(),
+                                                      cache,
+                                                      &mut rng)));
+            }
+            runner.add_unrated_population_as_niche(pop);
+        }
+        runner
+    }
+
+    fn mater<'a>(cache: &'a mut GlobalInnovationCache,
+                 element_strategy: &'a ES)
+                 -> Mater<'a, Neuron, ES, &'a mut GlobalInnovationCache> {
+        Mater {
+            p_crossover: Prob::new(0.5),
+            p_crossover_detail: ProbabilisticCrossover {
+                prob_match_left: Prob::new(0.5),
+                prob_disjoint_left: Prob::new(0.9),
+                prob_excess_left: Prob::new(0.9),
+                prob_disjoint_right: Prob::new(0.15),
+                prob_excess_right: Prob::new(0.15),
+                blend: BlendMethod::Select,
+            },
+            p_mutate_element: Prob::new(0.1),
+            weight_perturbance: WeightPerturbanceMethod::JiggleUniform {
+                range: WeightRange::bipolar(0.1),
+            },
+            self_adaptation: None,
+            mutate_weights: MutateMethodWeighting {
+                w_modify_weight: 10,
+                w_add_connection: 10,
+                w_enable_connection: 1,
+                w_delete_connection: 1,
+                w_delete_node: 1,
+                w_add_node: 10,
+                w_change_node_type: 0,
+                w_modify_node_params: 0,
+            },
+            mutation_schedule: MutationSchedule::single(),
+            bridge_deleted_nodes: true,
+            prune_offspring: None,
+            validate_offspring: true,
+            global_cache: cache,
+            element_strategy: element_strategy,
+            _n: PhantomData,
+        }
+    }
+
+    /// Reproduces the niches of `runner` until iteration `max_iterations`.
+
+    fn evolve(runner: &mut NicheRunner<Genome<Neuron>, Size>,
+              cache: &mut GlobalInnovationCache,
+              master: &SeedStream,
+              max_iterations: usize) {
+        let element_strategy = ES;
+        let mut mater = mater(cache, &element_strategy);
+        while runner.has_next_iteration(max_iterations) {
+            let generation = runner.current_iteration() as u64;
+            runner.reproduce_niche_locally_seeded(30,
+                                                  Closed01::new(0.2),
+                                                  Closed01::new(0.5),
+                                                  &mut mater,
+                                                  &master.split(generation));
+            for niche in runner.niches.niches.iter_mut() {
+                niche.log_fitness();
+            }
+        }
+    }
+
+    /// Like `evolve`, but redistributes the niches which did not improve after each
+    /// reproduction. Each generation derives the seed of the reproduction and the random
+    /// numbers of the redistribution from `master`. Returns the number of redistributed niches.
+
+    fn evolve_redistributing(runner: &mut NicheRunner<Genome<Neuron>, Size>,
+                             cache: &mut GlobalInnovationCache,
+                             master: &SeedStream,
+                             max_iterations: usize)
+                             -> usize {
+        let element_strategy = ES;
+        let compatibility = GenomeDistance {
+            excess: 1.0,
+            disjoint: 1.0,
+            weight: 0.5,
+            node_params: 0.0,
+            node_excess: 1.0,
+            node_disjoint: 1.0,
+            node_type: 0.5,
+        };
+        let mut mater = mater(cache, &element_strategy);
+        let mut redistributes = 0;
+        while runner.has_next_iteration(max_iterations) {
+            let generation_seed = master.split(runner.current_iteration() as u64);
+            runner.reproduce_niche_locally_seeded(30,
+                                                  Closed01::new(0.2),
+                                                  Closed01::new(0.5),
+                                                  &mut mater,
+                                                  &generation_seed.split(0));
+            // no niche improves that much, so every niche with a fitness log of two entries
+            // is redistributed.
+            let mut rng = generation_seed.split(1).rng();
+            redistributes += runner.redistribute_niches_with_no_improvement(1000.0,
+                                                                            2,
+                                                                            3,
+                                                                            1.0,
+                                                                            &compatibility,
+                                                                            &mut rng);
+        }
+        redistributes
+    }
+
+    /// Runs a few generations within a thread pool of `num_threads` threads and returns the
+    /// final niches in textual form.
+
+    fn run(seed: u64, num_threads: usize) -> String {
+        let pool = ThreadPool::new(Configuration::new().set_num_threads(num_threads)).unwrap();
+        pool.install(|| {
+            let master = SeedStream::new(seed);
+            let mut cache = GlobalInnovationCache::new();
+            let fitness = Size;
+            let mut runner = initial_runner(&fitness, &mut cache, &master);
+            evolve(&mut runner, &mut cache, &master, 7);
+            format!("{:?}", runner.niches)
+        })
+    }
+
+    #[test]
+    fn test_reproduce_niches_seeded() {
+        let single = run(1, 1);
+
+        // the result neither depends on the number of threads, nor on the scheduling of the
+        // niches.
+        assert_eq!(single, run(1, 4));
+        assert_eq!(single, run(1, 4));
+
+        assert!(single != run(2, 4));
+    }
+
+    /// Maps each pair of linked nodes to it's link innovation. Panics if the niches of `runner`
+    /// use different innovations for the same pair, or the same innovation for different pairs.
+
+    fn link_innovations(runner: &NicheRunner<Genome<Neuron>, Size>)
+                        -> BTreeMap<(NodeInnovation, NodeInnovation), LinkInnovation> {
+        let mut innovations = BTreeMap::new();
+        let mut pairs = BTreeMap::new();
+        for niche in runner.niches.niches.iter() {
+            for ind in niche.population.individuals.iter() {
+                ind.genome.network().each_link_ref(|link_ref| {
+                    let pair: (NodeInnovation, NodeInnovation) =
+                        (link_ref.external_source_node_id().into(),
+                         link_ref.external_target_node_id().into());
+                    let link: LinkInnovation = link_ref.external_link_id().into();
+                    assert_eq!(link, *innovations.entry(pair).or_insert(link));
+                    assert_eq!(pair, *pairs.entry(link).or_insert(pair));
+                });
+            }
+        }
+        innovations
+    }
+
+    #[test]
+    fn test_reproduce_niches_par_innovations() {
+        let master = SeedStream::new(7);
+        let fitness = Size;
+        let mut cache = GlobalInnovationCache::new();
+        let mut runner = initial_runner(&fitness, &mut cache, &master);
+        let initial_links = link_innovations(&runner).len();
+
+        // all niches share the innovations of one cache, while they are reproduced in parallel.
+        let shared = ConcurrentInnovationCache::from_cache(cache);
+        let element_strategy = ES;
+        let mut unused = GlobalInnovationCache::new();
+        let mater = mater(&mut unused, &element_strategy).with_global_cache(&shared);
+        let mut rng = master.split(1).rng();
+
+        let pool = ThreadPool::new(Configuration::new().set_num_threads(4)).unwrap();
+        pool.install(|| {
+            while runner.has_next_iteration(10) {
+                runner.reproduce_niche_locally_par(30,
+                                                   Closed01::new(0.2),
+                                                   Closed01::new(0.5),
+                                                   &mater,
+                                                   &mut rng);
+            }
+        });
+
+        let innovations = link_innovations(&runner);
+        assert!(innovations.len() > initial_links);
+
+        // the cache hands out the innovations found in the niches.
+        let mut cache = &shared;
+        for (&(source_node, target_node), &link) in innovations.iter() {
+            assert_eq!(link, cache.get_or_create_link_innovation(source_node, target_node));
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_resume() {
+        let master = SeedStream::new(3);
+        let fitness = Size;
+        let mut cache = GlobalInnovationCache::new();
+        let mut runner = initial_runner(&fitness, &mut cache, &master);
+        evolve(&mut runner, &mut cache, &master, 3);
+
+        let mut checkpoint = Vec::new();
+        runner.write_checkpoint(&(cache.clone(), master), &mut checkpoint).unwrap();
+        let checkpoint = String::from_utf8(checkpoint).unwrap();
+
+        // continue without interruption
+        evolve(&mut runner, &mut cache, &master, 7);
+
+        let resumed: (NicheRunner<Genome<Neuron>, Size>, (GlobalInnovationCache, SeedStream)) =
+            NicheRunner::read_checkpoint(&fitness, &checkpoint).unwrap();
+        let (mut resumed, (mut resumed_cache, resumed_master)) = resumed;
+        assert_eq!(3, resumed.current_iteration());
+        assert_eq!(master, resumed_master);
+        evolve(&mut resumed, &mut resumed_cache, &resumed_master, 7);
+
+        assert_eq!(format!("{:?}", runner.niches), format!("{:?}", resumed.niches));
+        assert_eq!(format!("{:?}", runner.genealogy()),
+                   format!("{:?}", resumed.genealogy()));
+        assert_eq!(format!("{:?}", cache), format!("{:?}", resumed_cache));
+
+        let truncated = &checkpoint[..checkpoint.len() / 2];
+        let result: Result<(NicheRunner<Genome<Neuron>, Size>, (GlobalInnovationCache, SeedStream)),
+                           PersistError> = NicheRunner::read_checkpoint(&fitness, truncated);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_resume_redistributing() {
+        let master = SeedStream::new(5);
+        let fitness = Size;
+        let mut cache = GlobalInnovationCache::new();
+        let mut runner = initial_runner(&fitness, &mut cache, &master);
+        assert!(evolve_redistributing(&mut runner, &mut cache, &master, 3) > 0);
+
+        let mut checkpoint = Vec::new();
+        runner.write_checkpoint(&(cache.clone(), master), &mut checkpoint).unwrap();
+        let checkpoint = String::from_utf8(checkpoint).unwrap();
+
+        // continue without interruption
+        assert!(evolve_redistributing(&mut runner, &mut cache, &master, 7) > 0);
+
+        let resumed: (NicheRunner<Genome<Neuron>, Size>, (GlobalInnovationCache, SeedStream)) =
+            NicheRunner::read_checkpoint(&fitness, &checkpoint).unwrap();
+        let (mut resumed, (mut resumed_cache, resumed_master)) = resumed;
+        evolve_redistributing(&mut resumed, &mut resumed_cache, &resumed_master, 7);
+
+        assert_eq!(format!("{:?}", runner.niches), format!("{:?}", resumed.niches));
+        assert_eq!(format!("{:?}", runner.genealogy()),
+                   format!("{:?}", resumed.genealogy()));
+        assert_eq!(format!("{:?}", cache), format!("{:?}", resumed_cache));
+    }
+
+    #[test]
+    fn test_prune_genealogy() {
+        let master = SeedStream::new(4);
+        let fitness = Size;
+        let mut cache = GlobalInnovationCache::new();
+        let mut runner = initial_runner(&fitness, &mut cache, &master);
+        evolve(&mut runner, &mut cache, &master, 5);
+
+        let len = runner.genealogy().len();
+        let live = runner.niches.lineage_ids();
+        assert_eq!(runner.num_individuals(), live.len());
+
+        let removed = runner.prune_genealogy();
+        assert!(removed > 0);
+        assert_eq!(len - removed, runner.genealogy().len());
+        assert_eq!(0, runner.prune_genealogy());
+
+        // the complete ancestry of the current population is kept.
+        for &id in live.iter() {
+            for record in runner.genealogy().ancestry(id) {
+                for parent in record.lineage.parents.iter() {
+                    assert!(runner.genealogy().get(*parent).is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lineage_parents() {
+        let master = SeedStream::new(6);
+        let fitness = Size;
+        let mut cache = GlobalInnovationCache::new();
+        let mut runner = initial_runner(&fitness, &mut cache, &master);
+        evolve(&mut runner, &mut cache, &master, 5);
+
+        // only offspring produced by crossover have two parents.
+        let mut crossovers = 0;
+        let mut mutations = 0;
+        for id in (0..runner.genealogy().len()).map(IndividualId) {
+            let lineage = &runner.genealogy().get(id).unwrap().lineage;
+            if lineage.operators.contains(&Operator::Crossover) {
+                crossovers += 1;
+                assert!(lineage.parents.len() <= 2);
+            } else {
+                let mutated = lineage.operators.iter().any(|op| {
+                    match *op {
+                        Operator::Mutate(_) => true,
+                        _ => false,
+                    }
+                });
+                if mutated {
+                    mutations += 1;
+                }
+                assert!(lineage.parents.len() <= 1);
+            }
+        }
+        assert!(crossovers > 0 && mutations > 0);
+    }
+
+    #[test]
+    fn test_archive_population() {
+        let master = SeedStream::new(5);
+        let fitness = Size;
+        let mut cache = GlobalInnovationCache::new();
+        let pop = initial_runner(&fitness, &mut cache, &master).into_population();
+
+        let mut buf = Vec::new();
+        save(&pop, &mut buf).unwrap();
+        let restored: Population<Genome<Neuron>, Rated> = load(&String::from_utf8(buf).unwrap())
+                                                              .unwrap();
+        assert_eq!(format!("{:?}", pop), format!("{:?}", restored));
+
+        let mut buf = Vec::new();
+        save(restored.best_individual().unwrap(), &mut buf).unwrap();
+        let champion: Individual<Genome<Neuron>> = load(&String::from_utf8(buf).unwrap()).unwrap();
+        assert_eq!(pop.best_individual().unwrap().fitness(), champion.fitness());
+
+        // reload the champion into a new run, where it starts a new lineage. The new run
+        // creates its input and output nodes in the same order as the old one, and registers
+        // the champion before any link is created.
+        let mut new_cache = GlobalInnovationCache::new();
+        let builder = GenomeBuilder::new(vec![Neuron::Input; 3],
+                                         vec![Neuron::Output; 2],
+                                         None,
+                                         &mut new_cache);
+        new_cache.register_genome(champion.genome());
+
+        let mut new_pop = Population::<_, Unrated>::new();
+        new_pop.add_genome(champion.into_genome());
+        let mut runner = NicheRunner::new(&fitness);
+        runner.add_unrated_population_as_niche(new_pop);
+        assert_eq!(Some(IndividualId(0)), runner.best_individual().lineage().id);
+        assert_eq!(pop.best_individual().unwrap().fitness(),
+                   runner.best_individual().fitness());
+
+        // genomes of the new run use the same link innovations as the champion.
+        let mut rng = master.split(1).rng();
+        let mut new_pop = Population::<_, Unrated>::new();
+        for _ in 0..10 {
+            new_pop.add_genome(Box::new(builder.build(InitialConnectivity::Sparse(Prob::new(0.5)),
+                                                      &ES.link_weight_range(),
+                                                      &mut new_cache,
+                                                      &mut rng)));
+        }
+        runner.add_unrated_population_as_niche(new_pop);
+        link_innovations(&runner);
+    }
+}
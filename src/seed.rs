@@ -0,0 +1,89 @@
+use rand::{XorShiftRng, SeedableRng};
+use persist::{Persist, Reader, PersistError};
+use std::io::{self, Write};
+
+/// A seed from which independent random number streams are derived.
+///
+/// A run is driven by a single master seed. From it, `split` derives a seed per generation, from
+/// that a seed per niche and so on. As each stream only depends on the master seed and it's
+/// path of indices, the random numbers a niche or an offspring receives do not depend on how
+/// many random numbers other niches or offspring consumed, or on which thread they run.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeedStream {
+    seed: u64,
+}
+
+impl SeedStream {
+    pub fn new(seed: u64) -> SeedStream {
+        SeedStream { seed: seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Derives the `index`th sub-stream.
+
+    pub fn split(&self, index: u64) -> SeedStream {
+        SeedStream { seed: mix(self.seed ^ mix(index)) }
+    }
+
+    /// Creates a random number generator for this stream.
+
+    pub fn rng(&self) -> XorShiftRng {
+        let a = mix(self.seed);
+        let b = mix(a);
+        let mut seed = [a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32];
+        // XorShiftRng must not be seeded with all zeros.
+        if seed.iter().all(|&s| s == 0) {
+            seed[0] = 1;
+        }
+        XorShiftRng::from_seed(seed)
+    }
+}
+
+/// Written as `(seed-stream seed)`. As all random numbers of a seeded run are derived from the
+/// master stream, persisting it is all it takes to continue a run with the same random numbers.
+
+impl Persist for SeedStream {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        write!(wr, "(seed-stream {}) ", self.seed)
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        try!(rd.open_keyword("seed-stream"));
+        let seed = try!(rd.atom());
+        try!(rd.close());
+        Ok(SeedStream::new(seed))
+    }
+}
+
+/// The SplitMix64 finalizer.
+
+fn mix(z: u64) -> u64 {
+    let z = z.wrapping_add(0x9e3779b97f4a7c15);
+    let z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SeedStream;
+    use rand::Rng;
+
+    #[test]
+    fn test_split() {
+        let master = SeedStream::new(42);
+        assert_eq!(master.split(3), SeedStream::new(42).split(3));
+        assert!(master.split(0) != master.split(1));
+        assert!(master.split(0).split(1) != master.split(1).split(0));
+
+        let a: Vec<u32> = master.split(7).rng().gen_iter().take(8).collect();
+        let b: Vec<u32> = master.split(7).rng().gen_iter().take(8).collect();
+        let c: Vec<u32> = master.split(8).rng().gen_iter().take(8).collect();
+        assert_eq!(a, b);
+        assert!(a != c);
+    }
+}
@@ -1,5 +1,6 @@
 use rand::Rng;
 use fitness::Fitness;
+use lineage::{Operator, Contributors};
 
 /// Measures the genetic distance. This can be applied on a variety of levels.
 /// For example, this is used to measure the genetic distance (or compatibility)
@@ -16,13 +17,60 @@ pub trait Genotype: Send + Clone {}
 /// There is no need to use both individuals. Instead it can also
 /// be used mutation only. Usually this is either crossover or mutation,
 /// or both.
+///
+/// `parent_left` performs at least as good as `parent_right`, i.e. `fitness_left >=
+/// fitness_right`. Parents of equal fitness can be treated symmetrically.
 pub trait Mate<T: Genotype> {
     fn mate<R: Rng>(&mut self,
                     parent_left: &T,
                     parent_right: &T,
+                    fitness_left: Fitness,
+                    fitness_right: Fitness,
                     prefer_mutate: bool,
                     rng: &mut R)
                     -> T;
+
+    /// Same as `mate`, but additionally appends the operators which produced the offspring to
+    /// `operators`, in the order they were applied, and returns which parents contributed to
+    /// the offspring. The default implementation records nothing and reports both parents.
+    fn mate_traced<R: Rng>(&mut self,
+                           parent_left: &T,
+                           parent_right: &T,
+                           fitness_left: Fitness,
+                           fitness_right: Fitness,
+                           prefer_mutate: bool,
+                           _operators: &mut Vec<Operator>,
+                           rng: &mut R)
+                           -> (T, Contributors) {
+        let offspring = self.mate(parent_left,
+                                  parent_right,
+                                  fitness_left,
+                                  fitness_right,
+                                  prefer_mutate,
+                                  rng);
+        (offspring, Contributors::Both)
+    }
+}
+
+/// A `Mate` which can be split into independent stages, one per niche, so that niches can be
+/// reproduced in parallel.
+///
+/// A stage must not modify state shared with other stages, like the global innovation cache.
+/// Instead it records the changes it would make. After all niches are reproduced, `commit`
+/// applies the changes of each stage, in niche order, and updates the genomes created by that
+/// stage accordingly. This way, the result does not depend on the order in which the stages
+/// were run.
+pub trait StagedMate<T: Genotype>: Mate<T> {
+    type Stage: Mate<T> + Send;
+
+    /// Creates `n` independent stages.
+    fn stage(&self, n: usize) -> Vec<Self::Stage>;
+
+    /// Applies the changes recorded by `stage`. `genomes` must include all genomes produced by
+    /// `stage` and may contain other genomes, which are left unchanged.
+    fn commit<'b, I>(&mut self, stage: Self::Stage, genomes: I)
+        where I: Iterator<Item = &'b mut T>,
+              T: 'b;
 }
 
 /// Trait to calculate the fitness for a genome.
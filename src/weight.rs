@@ -1,7 +1,11 @@
-use rand::{Rng, Closed01};
+use rand::{Rng, Closed01, Open01};
+use rand::distributions::{Normal, IndependentSample};
+use std::f64::consts::PI;
+use std::io::{self, Write};
+use persist::{Persist, Reader, PersistError};
 
 /// Represents a connection weight.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Weight(pub f64);
 
 impl Into<f64> for Weight {
@@ -16,6 +20,16 @@ impl Into<f32> for Weight {
     }
 }
 
+impl Persist for Weight {
+    fn persist<W: Write>(&self, wr: &mut W) -> io::Result<()> {
+        self.0.persist(wr)
+    }
+
+    fn restore(rd: &mut Reader) -> Result<Self, PersistError> {
+        rd.atom().map(Weight)
+    }
+}
+
 /// Represents the range of a connection weight. The range is closed,
 /// i.e. including both endpoints [low, high].
 #[derive(Debug, Clone, Copy)]
@@ -94,13 +108,30 @@ impl WeightRange {
 }
 
 /// Defines a perturbance method.
+///
+/// All methods except `Random` add a random offset to the weight, which is then clipped into the
+/// weight range.
+#[derive(Debug, Clone, Copy)]
 pub enum WeightPerturbanceMethod {
+    /// Offset uniformly distributed within `range`.
     JiggleUniform {
         range: WeightRange,
     },
+    /// Offset normal distributed with mean 0.0 and standard deviation `sigma`.
     JiggleGaussian {
         sigma: f64,
     },
+    /// Offset Cauchy distributed with median 0.0 and scale `scale`. Compared to `JiggleGaussian`,
+    /// large offsets are much more likely.
+    JiggleCauchy {
+        scale: f64,
+    },
+    /// Polynomial mutation with distribution index `eta`. The offset is relative to the size
+    /// of the weight range. Larger values of `eta` produce smaller offsets.
+    Polynomial {
+        eta: f64,
+    },
+    /// Replace the weight by a random weight.
     Random,
 }
 
@@ -115,7 +146,55 @@ impl WeightPerturbanceMethod {
             WeightPerturbanceMethod::JiggleUniform { range } => {
                 weight_range.clip_weight(Weight(weight.0 + range.random_weight(rng).0))
             }
-            WeightPerturbanceMethod::JiggleGaussian {..} => unimplemented!(),
+            WeightPerturbanceMethod::JiggleGaussian { sigma } => {
+                assert!(sigma > 0.0);
+                let offset = Normal::new(0.0, sigma).ind_sample(rng);
+                weight_range.clip_weight(Weight(weight.0 + offset))
+            }
+            WeightPerturbanceMethod::JiggleCauchy { scale } => {
+                assert!(scale > 0.0);
+                // inverse transform sampling
+                let u = rng.gen::<Open01<f64>>().0;
+                let offset = scale * (PI * (u - 0.5)).tan();
+                weight_range.clip_weight(Weight(weight.0 + offset))
+            }
+            WeightPerturbanceMethod::Polynomial { eta } => {
+                assert!(eta >= 0.0);
+                let u = rng.gen::<Closed01<f64>>().0;
+                let delta = if u < 0.5 {
+                    (2.0 * u).powf(1.0 / (eta + 1.0)) - 1.0
+                } else {
+                    1.0 - (2.0 * (1.0 - u)).powf(1.0 / (eta + 1.0))
+                };
+                debug_assert!(delta >= -1.0 && delta <= 1.0);
+                let offset = delta * (weight_range.high - weight_range.low);
+                weight_range.clip_weight(Weight(weight.0 + offset))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Weight, WeightRange, WeightPerturbanceMethod};
+    use rand;
+
+    #[test]
+    fn test_perturb_clips_weight() {
+        let mut rng = rand::thread_rng();
+        let weight_range = WeightRange::bipolar(1.0);
+        let methods = [WeightPerturbanceMethod::JiggleUniform { range: WeightRange::bipolar(0.5) },
+                       WeightPerturbanceMethod::JiggleGaussian { sigma: 2.0 },
+                       WeightPerturbanceMethod::JiggleCauchy { scale: 2.0 },
+                       WeightPerturbanceMethod::Polynomial { eta: 0.0 },
+                       WeightPerturbanceMethod::Random];
+
+        for method in methods.iter() {
+            let mut weight = Weight(0.9);
+            for _ in 0..1000 {
+                weight = method.perturb(weight, &weight_range, &mut rng);
+                assert!(weight_range.in_range(weight));
+            }
         }
     }
 }